@@ -0,0 +1,418 @@
+//!
+//! # Async helpers
+//!
+//! Non-blocking counterparts of `sleep_ms!` and [`retry`](crate::common::retry),
+//! and a [`timeout`] combinator, usable in any executor:
+//! the delays are driven by one shared background timer thread,
+//! no runtime is required.
+//!
+//! ```
+//! use ruc::{aio, common::RetryPolicy, *};
+//! use std::time::Duration;
+//!
+//! async fn fetch() -> Result<u32> {
+//!     aio::sleep_ms(10).await;
+//!     Ok(1)
+//! }
+//!
+//! async fn fetch_with_retry() -> Result<u32> {
+//!     let policy = RetryPolicy::new(3).base_delay(Duration::from_millis(100));
+//!     aio::retry_async(&policy, || async {
+//!         aio::timeout(Duration::from_secs(1), fetch()).await?
+//!     })
+//!     .await
+//!     .c(d!("fetch"))
+//! }
+//! ```
+//!
+
+use crate::{
+    common::{RetryPolicy, RetryState},
+    err::{Result, SimpleError, SimpleMsg},
+};
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    panic::Location,
+    pin::Pin,
+    sync::{
+        Arc, Condvar, LazyLock, Mutex, Weak,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+/// Wait for `ms` milliseconds without blocking the thread
+#[inline(always)]
+pub fn sleep_ms(ms: u64) -> Sleep {
+    sleep(Duration::from_millis(ms))
+}
+
+/// Wait for `d` without blocking the thread,
+/// a `d` too large for an `Instant`(e.g. `Duration::MAX`) never ends
+#[inline(always)]
+pub fn sleep(d: Duration) -> Sleep {
+    Sleep {
+        at: Instant::now().checked_add(d),
+        shared: None,
+    }
+}
+
+/// The future returned by [`sleep`] and [`sleep_ms`]
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Sleep {
+    // `None` if out of the range of `Instant`
+    at: Option<Instant>,
+    // registered to the timer on the first pending poll
+    shared: Option<Arc<Shared>>,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let Some(at) = self.at else {
+            return Poll::Pending;
+        };
+        if at <= Instant::now() {
+            return Poll::Ready(());
+        }
+        let shared = self
+            .shared
+            .get_or_insert_with(|| {
+                let shared = Arc::new(Shared::default());
+                TIMER.register(at, Arc::downgrade(&shared));
+                shared
+            })
+            .clone();
+        *shared.waker.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some(cx.waker().clone());
+        // the timer may have fired before the waker was stored
+        if shared.done.load(Ordering::Acquire) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// Run `fut` within `d`, or fail with an error
+/// located at the caller of this function,
+/// `fut` is dropped on expiry; `Duration::MAX` means no limit
+#[track_caller]
+pub fn timeout<F: Future>(d: Duration, fut: F) -> Timeout<F> {
+    Timeout {
+        fut: Box::pin(fut),
+        sleep: sleep(d),
+        limit: d,
+        loc: Location::caller(),
+    }
+}
+
+/// The future returned by [`timeout`]
+#[must_use = "futures do nothing unless polled"]
+pub struct Timeout<F> {
+    fut: Pin<Box<F>>,
+    sleep: Sleep,
+    limit: Duration,
+    loc: &'static Location<'static>,
+}
+
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output>;
+
+    fn poll(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Self::Output> {
+        if let Poll::Ready(v) = self.fut.as_mut().poll(cx) {
+            return Poll::Ready(Ok(v));
+        }
+        match Pin::new(&mut self.sleep).poll(cx) {
+            Poll::Ready(()) => {
+                let msg = SimpleMsg::new(
+                    format!("timeout after {}ms", self.limit.as_millis()),
+                    self.loc.file(),
+                    self.loc.line(),
+                    self.loc.column(),
+                );
+                Poll::Ready(Err(Box::new(SimpleError::new(msg, None))))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// The async version of [`RetryPolicy::run`],
+/// the delays are spent in [`sleep`].
+#[track_caller]
+pub fn retry_async<'a, T, F, Fut>(
+    policy: &'a RetryPolicy,
+    mut f: F,
+) -> impl Future<Output = Result<T>> + 'a
+where
+    F: FnMut() -> Fut + 'a,
+    Fut: Future<Output = Result<T>> + 'a,
+{
+    let loc = Location::caller();
+    async move {
+        let mut state = RetryState::new(policy, loc);
+        loop {
+            match f().await {
+                Ok(v) => return Ok(v),
+                Err(e) => sleep(state.failed(e)?).await,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    done: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Shared {
+    fn fire(&self) {
+        self.done.store(true, Ordering::Release);
+        let waker =
+            self.waker.lock().unwrap_or_else(|e| e.into_inner()).take();
+        if let Some(w) = waker {
+            w.wake();
+        }
+    }
+}
+
+struct Entry {
+    at: Instant,
+    // dead once the `Sleep` is dropped
+    shared: Weak<Shared>,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.at == other.at
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.at.cmp(&other.at)
+    }
+}
+
+// A single thread firing the registered sleeps in time order,
+// started on the first use.
+//
+// The queue only holds weak handles, so a dropped `Sleep` releases
+// its waker at once, its entry is skipped when it comes first,
+// or pruned when the queue has doubled since the last pruning.
+#[derive(Default)]
+struct Timer {
+    queue: Mutex<Queue>,
+    cond: Condvar,
+}
+
+#[derive(Default)]
+struct Queue {
+    heap: BinaryHeap<Reverse<Entry>>,
+    prune_at: usize,
+}
+
+impl Queue {
+    fn push(&mut self, e: Entry) {
+        if self.prune_at <= self.heap.len() {
+            self.heap.retain(|Reverse(e)| 0 < e.shared.strong_count());
+            self.prune_at = 64.max(2 * self.heap.len());
+        }
+        self.heap.push(Reverse(e));
+    }
+}
+
+static TIMER: LazyLock<Arc<Timer>> = LazyLock::new(|| {
+    let timer = Arc::new(Timer::default());
+    let t = Arc::clone(&timer);
+    std::thread::Builder::new()
+        .name("ruc-timer".to_owned())
+        .spawn(move || t.run())
+        .expect("failed to spawn the timer thread");
+    timer
+});
+
+impl Timer {
+    fn register(&self, at: Instant, shared: Weak<Shared>) {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        let earliest = queue.heap.peek().is_none_or(|Reverse(e)| at < e.at);
+        queue.push(Entry { at, shared });
+        drop(queue);
+        if earliest {
+            self.cond.notify_one();
+        }
+    }
+
+    fn run(&self) {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            let now = Instant::now();
+            let mut due = vec![];
+            while let Some(Reverse(e)) = queue.heap.peek() {
+                if now < e.at && 0 < e.shared.strong_count() {
+                    break;
+                }
+                if let Some(Reverse(e)) = queue.heap.pop() {
+                    due.extend(e.shared.upgrade());
+                }
+            }
+            if !due.is_empty() {
+                // wake without holding the lock,
+                // the woken tasks may register new sleeps at once
+                drop(queue);
+                due.iter().for_each(|s| s.fire());
+                queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+                continue;
+            }
+            queue = match queue.heap.peek() {
+                Some(Reverse(e)) => {
+                    let wait = e.at.saturating_duration_since(now);
+                    self.cond
+                        .wait_timeout(queue, wait)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+                None => {
+                    self.cond.wait(queue).unwrap_or_else(|e| e.into_inner())
+                }
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::*;
+    use std::{sync::atomic::AtomicUsize, task::Wake, thread::Thread};
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    // a minimal executor, to avoid depending on any runtime
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        let mut fut = std::pin::pin!(fut);
+        let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(v) = fut.as_mut().poll(&mut cx) {
+                return v;
+            }
+            std::thread::park();
+        }
+    }
+
+    #[test]
+    fn t_sleep() {
+        let start = Instant::now();
+        block_on(sleep_ms(30));
+        assert!(Duration::from_millis(30) <= start.elapsed());
+        block_on(sleep(Duration::ZERO));
+
+        // out of order registrations
+        let start = Instant::now();
+        let hs = [60, 20, 40]
+            .map(|ms| std::thread::spawn(move || block_on(sleep_ms(ms))));
+        hs.into_iter().for_each(|h| h.join().unwrap());
+        let elapsed = start.elapsed();
+        assert!(Duration::from_millis(60) <= elapsed);
+        assert!(elapsed < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn t_sleep_dropped() {
+        let waker = Arc::new(ThreadWaker(std::thread::current()));
+        let w = Waker::from(Arc::clone(&waker));
+        let mut s = sleep(Duration::from_secs(3600));
+        let mut cx = Context::from_waker(&w);
+        assert!(Pin::new(&mut s).poll(&mut cx).is_pending());
+        drop(w);
+        assert_eq!(Arc::strong_count(&waker), 2);
+        // released at once, not an hour later
+        drop(s);
+        assert_eq!(Arc::strong_count(&waker), 1);
+
+        let timer = Timer::default();
+        for _ in 0..1000 {
+            let shared = Arc::new(Shared::default());
+            timer.register(Instant::now(), Arc::downgrade(&shared));
+        }
+        assert!(timer.queue.lock().unwrap().heap.len() <= 64);
+    }
+
+    #[test]
+    fn t_sleep_forever() {
+        let waker = Waker::noop();
+        let mut cx = Context::from_waker(waker);
+        let mut s = sleep(Duration::MAX);
+        assert!(Pin::new(&mut s).poll(&mut cx).is_pending());
+
+        // no timeout at all
+        let v = block_on(timeout(Duration::MAX, async {
+            sleep_ms(1).await;
+            1
+        }));
+        assert_eq!(v.unwrap(), 1);
+    }
+
+    #[test]
+    fn t_timeout() {
+        let v = block_on(timeout(Duration::from_secs(5), async {
+            sleep_ms(1).await;
+            1
+        }));
+        assert_eq!(v.unwrap(), 1);
+
+        let line = line!() + 1;
+        let e = block_on(timeout(Duration::from_millis(10), sleep_ms(5000)))
+            .unwrap_err();
+        assert_eq!(e.get_top_msg(), "timeout after 10ms");
+        let (file, l, _) = e.location().unwrap();
+        assert_eq!((file, l), (file!(), line));
+    }
+
+    #[test]
+    fn t_retry_async() {
+        let n = AtomicUsize::new(0);
+        let policy = RetryPolicy::new(3).base_delay(Duration::from_millis(5));
+        let v = block_on(retry_async(&policy, || async {
+            if n.fetch_add(1, Ordering::Relaxed) < 2 {
+                Err(eg!("flaky"))
+            } else {
+                Ok(7)
+            }
+        }));
+        assert_eq!(v.unwrap(), 7);
+
+        let e: Box<dyn RucError> = block_on(retry_async(&policy, || async {
+            timeout(Duration::from_millis(1), sleep_ms(1000)).await?;
+            Ok(())
+        }))
+        .unwrap_err();
+        assert_eq!(e.branches().len(), 3);
+        assert_eq!(e.location().unwrap().0, file!());
+        assert_eq!(e.branches()[2].get_top_msg(), "timeout after 1ms");
+    }
+}
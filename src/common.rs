@@ -3,9 +3,31 @@
 //!
 //! Common and lightweight utils.
 //!
+//! Only the pure macros(`max!`, `ensure!`, etc.) are available without `std`.
+//!
 
+#[cfg(feature = "std")]
+mod datetime;
+#[cfg(feature = "std")]
+mod env;
+#[cfg(feature = "std")]
+mod fs;
+#[cfg(feature = "std")]
+mod retry;
+
+#[cfg(feature = "std")]
+pub use datetime::*;
+#[cfg(feature = "std")]
+pub use env::*;
+#[cfg(feature = "std")]
+pub use fs::*;
+#[cfg(feature = "std")]
+pub use retry::*;
+
+#[cfg(feature = "std")]
 use crate::*;
-use std::{fmt::Display, path::Path};
+#[cfg(feature = "std")]
+use std::{fmt::Display, time::Duration};
 
 /// HashMap literal, e.g. `map!{1 => 2}`.
 ///
@@ -133,41 +155,6 @@ macro_rules! ts_ms {
     }};
 }
 
-/// Cached local UTC offset, detected once at first access.
-/// Falls back to UTC if detection fails (e.g., in multi-threaded context).
-static LOCAL_OFFSET: std::sync::LazyLock<time::UtcOffset> =
-    std::sync::LazyLock::new(|| {
-        time::UtcOffset::current_local_offset().unwrap_or(time::UtcOffset::UTC)
-    });
-
-static DATETIME_FORMAT: std::sync::LazyLock<
-    Vec<time::format_description::FormatItem<'static>>,
-> = std::sync::LazyLock::new(|| {
-    time::format_description::parse_borrowed::<2>("[year]-[month]-[day] [hour]:[minute]:[second] [offset_hour sign:mandatory]").unwrap()
-});
-
-/// Generate a formatted DateTime string
-///
-/// Timestamps outside the representable datetime range are saturated
-/// to the minimum/maximum representable datetime instead of panicking.
-#[inline(always)]
-pub fn gen_datetime(ts: i64) -> String {
-    // 1-day margin keeps the later `to_offset` conversion
-    // in range for any possible local offset
-    let margin = 86_400;
-    let min =
-        time::PrimitiveDateTime::MIN.assume_utc().unix_timestamp() + margin;
-    let max =
-        time::PrimitiveDateTime::MAX.assume_utc().unix_timestamp() - margin;
-
-    // safe: ts is clamped into the representable range
-    time::OffsetDateTime::from_unix_timestamp(ts.clamp(min, max))
-        .unwrap()
-        .to_offset(*LOCAL_OFFSET)
-        .format(&*DATETIME_FORMAT)
-        .unwrap()
-}
-
 /// get current DateTime
 #[macro_export]
 macro_rules! datetime {
@@ -175,49 +162,25 @@ macro_rules! datetime {
     () => {{ $crate::datetime!($crate::ts!()) }};
 }
 
-/// Get an environment variable or return a default value
-#[inline(always)]
-pub fn env_or(key: &str, default: &str) -> String {
-    std::env::var(key).unwrap_or_else(|_| default.to_owned())
-}
-
-/// Read a file to string, wrapping errors with ruc error chain
-#[inline(always)]
-pub fn read_file<P: AsRef<Path>>(path: P) -> Result<String> {
-    std::fs::read_to_string(path.as_ref()).c(d!())
-}
-
-/// Write contents to a file, wrapping errors with ruc error chain
-#[inline(always)]
-pub fn write_file<P: AsRef<Path>>(
-    path: P,
-    content: impl AsRef<[u8]>,
-) -> Result<()> {
-    std::fs::write(path.as_ref(), content).c(d!())
-}
-
 /// Retry a fallible operation with fixed delay between attempts.
 ///
-/// Returns the first successful result, or the last error wrapped in ruc error chain.
+/// A shortcut of [`RetryPolicy`], the returned error holds
+/// the errors of all the attempts as its branches,
+/// each one kept as-is for downcasting.
+#[cfg(feature = "std")]
+#[track_caller]
 pub fn retry<T, E, F>(times: usize, delay_ms: u64, mut f: F) -> Result<T>
 where
     F: FnMut() -> core::result::Result<T, E>,
     E: Display + Send + 'static,
 {
-    let total = times.max(1);
-    let mut last_err = None;
-    for i in 0..total {
-        match f() {
-            Ok(v) => return Ok(v),
-            Err(e) => {
-                last_err = Some(e);
-                if i + 1 < total && delay_ms > 0 {
-                    sleep_ms!(delay_ms);
-                }
-            }
-        }
-    }
-    Err(eg!(last_err.unwrap()))
+    let mut attempt = 0;
+    RetryPolicy::new(times)
+        .base_delay(Duration::from_millis(delay_ms))
+        .run(|| {
+            attempt += 1;
+            f().c(d!("attempt {}", attempt))
+        })
 }
 
 /// Ensure a condition is true, otherwise return an error.
@@ -251,8 +214,155 @@ macro_rules! ensure {
     }};
 }
 
+/// Return an error immediately, accepts the same arguments as `eg!`.
+///
+/// # Examples
+///
+/// ```
+/// use ruc::*;
+///
+/// fn parse(s: &str) -> Result<u8> {
+///     if s.is_empty() {
+///         bail!(code = 400, "empty input");
+///     }
+///     s.parse::<u8>().c(d!())
+/// }
+/// assert_eq!(parse("").unwrap_err().code(), Some(400));
+/// ```
+#[macro_export]
+macro_rules! bail {
+    ($($arg:tt)*) => {{
+        return Err($crate::eg!($($arg)*));
+    }};
+}
+
+/// Ensure two values are equal(`PartialEq`), otherwise return an error
+/// with both of them formatted by `Debug`, like `assert_eq!`.
+///
+/// # Examples
+///
+/// ```
+/// use ruc::*;
+///
+/// fn check(len: usize) -> Result<()> {
+///     ensure_eq!(len % 4, 0);
+///     ensure_eq!(len, 8, "unexpected key length");
+///     Ok(())
+/// }
+/// assert!(check(8).is_ok());
+/// assert!(check(7).unwrap_err().get_top_msg().contains("left: 3"));
+/// ```
+#[macro_export]
+macro_rules! ensure_eq {
+    ($left:expr, $right:expr $(,)?) => {{
+        $crate::ensure_eq!(
+            $left,
+            $right,
+            "ensure_eq failed: `{} == {}`",
+            stringify!($left),
+            stringify!($right)
+        )
+    }};
+    ($left:expr, $right:expr, $($arg:tt)+) => {{
+        match (&$left, &$right) {
+            (l, r) => {
+                if !(*l == *r) {
+                    return Err($crate::eg!(
+                        "{} (left: {:?}, right: {:?})",
+                        $crate::__private::format!($($arg)+),
+                        l,
+                        r
+                    ));
+                }
+            }
+        }
+    }};
+}
+
+/// Ensure two values are not equal(`PartialEq`), otherwise return an error
+/// with both of them formatted by `Debug`, like `assert_ne!`.
+///
+/// # Examples
+///
+/// ```
+/// use ruc::*;
+///
+/// fn check(port: u16) -> Result<()> {
+///     ensure_ne!(port, 0, "port must be assigned");
+///     Ok(())
+/// }
+/// assert!(check(0).is_err());
+/// ```
+#[macro_export]
+macro_rules! ensure_ne {
+    ($left:expr, $right:expr $(,)?) => {{
+        $crate::ensure_ne!(
+            $left,
+            $right,
+            "ensure_ne failed: `{} != {}`",
+            stringify!($left),
+            stringify!($right)
+        )
+    }};
+    ($left:expr, $right:expr, $($arg:tt)+) => {{
+        match (&$left, &$right) {
+            (l, r) => {
+                if *l == *r {
+                    return Err($crate::eg!(
+                        "{} (left: {:?}, right: {:?})",
+                        $crate::__private::format!($($arg)+),
+                        l,
+                        r
+                    ));
+                }
+            }
+        }
+    }};
+}
+
+/// Ensure a value matches a pattern(with an optional guard),
+/// otherwise return an error with the value formatted by `Debug`.
+///
+/// # Examples
+///
+/// ```
+/// use ruc::*;
+///
+/// fn check(v: Option<u32>) -> Result<()> {
+///     ensure_matches!(v, Some(n) if n > 0, "need a positive value");
+///     Ok(())
+/// }
+/// assert!(check(Some(1)).is_ok());
+/// assert!(check(None).unwrap_err().get_top_msg().contains("None"));
+/// ```
+#[macro_export]
+macro_rules! ensure_matches {
+    ($value:expr, $pat:pat $(if $guard:expr)? $(,)?) => {{
+        $crate::ensure_matches!(
+            $value,
+            $pat $(if $guard)?,
+            "ensure_matches failed: `{}` does not match `{}`",
+            stringify!($value),
+            stringify!($pat $(if $guard)?)
+        )
+    }};
+    ($value:expr, $pat:pat $(if $guard:expr)?, $($arg:tt)+) => {{
+        match $value {
+            $pat $(if $guard)? => {}
+            ref v => {
+                return Err($crate::eg!(
+                    "{} (value: {:?})",
+                    $crate::__private::format!($($arg)+),
+                    v
+                ));
+            }
+        }
+    }};
+}
+
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "std")]
     use super::*;
 
     #[test]
@@ -301,29 +411,6 @@ mod tests {
         assert!(s.contains(&1) && s.contains(&2));
     }
 
-    #[cfg(unix)]
-    #[test]
-    fn t_local_offset() {
-        let output = std::process::Command::new("date")
-            .arg("+%z")
-            .output()
-            .expect("failed to execute `date`");
-        let tz_str = std::str::from_utf8(&output.stdout).unwrap().trim();
-
-        // Parse "+0800" or "-0500" format
-        let sign: i32 = if tz_str.starts_with('-') { -1 } else { 1 };
-        let hours: i32 = tz_str[1..3].parse().unwrap();
-        let minutes: i32 = tz_str[3..5].parse().unwrap();
-        let expected = sign * (hours * 3600 + minutes * 60);
-
-        let detected = super::LOCAL_OFFSET.whole_seconds();
-        assert_eq!(
-            detected, expected,
-            "LOCAL_OFFSET ({}s) does not match OS timezone ({})",
-            detected, tz_str
-        );
-    }
-
     #[test]
     fn t_bmap_bset() {
         let m1 = bmap! {"a" => 1};
@@ -364,26 +451,7 @@ mod tests {
         assert!(ms / 1000 <= s + 2);
     }
 
-    // out-of-range timestamps saturate instead of panicking
-    #[test]
-    fn t_gen_datetime_extreme_ts() {
-        assert!(!gen_datetime(0).is_empty());
-        assert!(!gen_datetime(i64::MAX).is_empty());
-        assert!(!gen_datetime(i64::MIN).is_empty());
-        // in-range boundary values must survive `to_offset` too
-        assert!(!gen_datetime(253402300799).is_empty()); // 9999-12-31 UTC
-        assert!(!gen_datetime(-377705116800).is_empty()); // 0000-01-01 UTC
-    }
-
-    #[test]
-    fn t_env_or() {
-        // SAFETY: test runs single-threaded, no concurrent env access
-        unsafe { std::env::set_var("RUC_TEST_ENV_OR", "hello") };
-        assert_eq!(env_or("RUC_TEST_ENV_OR", "default"), "hello");
-        unsafe { std::env::remove_var("RUC_TEST_ENV_OR") };
-        assert_eq!(env_or("RUC_TEST_ENV_OR_NONEXIST", "default"), "default");
-    }
-
+    #[cfg(feature = "std")]
     #[test]
     fn t_retry_success() {
         let mut count = 0;
@@ -399,12 +467,22 @@ mod tests {
         assert_eq!(count, 3);
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn t_retry_failure() {
         let result = retry(2, 0, || -> core::result::Result<i32, String> {
             Err("fail".to_owned())
         });
         assert!(result.is_err());
+
+        let e = retry(2, 0, || "x".parse::<u8>()).unwrap_err();
+        assert_eq!(e.branches().len(), 2);
+        assert_eq!(e.branches()[1].get_top_msg(), "attempt 2");
+        assert!(
+            e.branches()[1]
+                .downcast_ref::<core::num::ParseIntError>()
+                .is_some()
+        );
     }
 
     #[test]
@@ -424,12 +502,36 @@ mod tests {
     }
 
     #[test]
-    fn t_file_utils() {
-        let dir = std::env::temp_dir();
-        let path = dir.join("ruc_test_file_utils.txt");
-        write_file(&path, "hello ruc").unwrap();
-        let content = read_file(&path).unwrap();
-        assert_eq!(content, "hello ruc");
-        std::fs::remove_file(&path).ok();
+    fn t_bail_ensure_eq() {
+        fn check(v: Option<u32>, len: usize) -> crate::err::Result<()> {
+            if 100 < len {
+                bail!(code = 413, "too long: {}", len);
+            }
+            ensure_eq!(len % 2, 0);
+            ensure_ne!(len, 0, "len of {:?}", v);
+            ensure_matches!(v, Some(n) if n < 10);
+            Ok(())
+        }
+        assert!(check(Some(1), 2).is_ok());
+
+        let e = check(None, 101).unwrap_err();
+        assert_eq!(e.code(), Some(413));
+        assert_eq!(e.get_top_msg(), "too long: 101");
+
+        let e = check(None, 3).unwrap_err();
+        assert_eq!(
+            e.get_top_msg(),
+            "ensure_eq failed: `len % 2 == 0` (left: 1, right: 0)"
+        );
+
+        let e = check(Some(1), 0).unwrap_err();
+        assert_eq!(e.get_top_msg(), "len of Some(1) (left: 0, right: 0)");
+
+        let e = check(Some(10), 2).unwrap_err();
+        assert_eq!(
+            e.get_top_msg(),
+            "ensure_matches failed: `v` does not match `Some(n) if n < 10` (value: Some(10))"
+        );
+        assert_eq!(e.location().unwrap().0, file!());
     }
 }
@@ -0,0 +1,456 @@
+//!
+//! # Date and time
+//!
+//! [`DateTimeFormat`] renders timestamps in the classic `datetime!` layout,
+//! in RFC 3339(a profile of ISO 8601), or in a custom format,
+//! in UTC or the local offset, with up to nanosecond precision;
+//! the same format parses its output back into a timestamp.
+//!
+//! ```
+//! use ruc::{common::*, *};
+//!
+//! let f = DateTimeFormat::rfc3339().utc().precision(Precision::Millis);
+//! assert_eq!(f.format_ts_ms(1_700_000_000_123), "2023-11-14T22:13:20.123Z");
+//! assert_eq!(parse_datetime_ms("2023-11-14T22:13:20.123Z")?, 1_700_000_000_123);
+//!
+//! let f = DateTimeFormat::custom("[day]/[month]/[year] [hour]:[minute]")?.utc();
+//! assert_eq!(f.format_ts(1_700_000_000), "14/11/2023 22:13");
+//! assert_eq!(f.parse_ts("14/11/2023 22:13")?, 1_699_999_980);
+//! # Ok::<(), Box<dyn RucError>>(())
+//! ```
+//!
+
+use crate::*;
+use std::{
+    sync::LazyLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use time::{
+    OffsetDateTime, PrimitiveDateTime, UtcOffset,
+    format_description::{
+        OwnedFormatItem, parse_owned,
+        well_known::{Iso8601, Rfc3339},
+    },
+};
+
+/// Cached local UTC offset, detected once at first access.
+/// Falls back to UTC if detection fails (e.g., in multi-threaded context).
+static LOCAL_OFFSET: LazyLock<UtcOffset> = LazyLock::new(|| {
+    UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC)
+});
+
+static DEFAULT_FORMAT: LazyLock<DateTimeFormat> =
+    LazyLock::new(DateTimeFormat::default);
+
+/// The time zone a [`DateTimeFormat`] renders in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tz {
+    /// the local offset, detected once at the first use
+    #[default]
+    Local,
+    /// `+00:00`
+    Utc,
+}
+
+impl Tz {
+    fn offset(self) -> UtcOffset {
+        match self {
+            Tz::Local => *LOCAL_OFFSET,
+            Tz::Utc => UtcOffset::UTC,
+        }
+    }
+}
+
+/// Digits of the fraction of a second
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Precision {
+    /// no fraction
+    #[default]
+    Secs,
+    /// 3 digits
+    Millis,
+    /// 6 digits
+    Micros,
+    /// 9 digits
+    Nanos,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Layout {
+    // `2021-09-09 09:23:56 +08`, `+05:30` if the offset has minutes
+    Classic,
+    // `2021-09-09T09:23:56+08:00` or `2021-09-09T01:23:56Z`
+    Rfc3339,
+    Custom(String),
+}
+
+/// How timestamps are rendered and parsed
+#[derive(Debug, Clone)]
+pub struct DateTimeFormat {
+    layout: Layout,
+    tz: Tz,
+    precision: Precision,
+    items: OwnedFormatItem,
+}
+
+impl PartialEq for DateTimeFormat {
+    fn eq(&self, other: &Self) -> bool {
+        (&self.layout, self.tz, self.precision)
+            == (&other.layout, other.tz, other.precision)
+    }
+}
+
+impl Eq for DateTimeFormat {}
+
+impl Default for DateTimeFormat {
+    /// The layout of `datetime!`, in the local offset,
+    /// e.g. `2021-09-09 09:23:56 +08`
+    fn default() -> Self {
+        Self::build(Layout::Classic, Tz::Local, Precision::Secs)
+    }
+}
+
+impl DateTimeFormat {
+    /// RFC 3339, e.g. `2021-09-09T09:23:56+08:00`, `Z` for UTC
+    pub fn rfc3339() -> Self {
+        Self::build(Layout::Rfc3339, Tz::Local, Precision::Secs)
+    }
+
+    /// A custom format in the syntax of the `time` crate,
+    /// e.g. `[year]/[month]/[day] [hour]:[minute]:[second].[subsecond]`;
+    /// the precision setting has no effect on it.
+    pub fn custom(fmt: &str) -> Result<Self> {
+        let items = parse_owned::<2>(fmt)
+            .c(d!("invalid datetime format: {:?}", fmt))?;
+        Ok(DateTimeFormat {
+            layout: Layout::Custom(fmt.to_owned()),
+            tz: Tz::Local,
+            precision: Precision::Secs,
+            items,
+        })
+    }
+
+    /// Render in UTC
+    pub fn utc(self) -> Self {
+        self.tz(Tz::Utc)
+    }
+
+    /// Render in the local offset
+    pub fn local(self) -> Self {
+        self.tz(Tz::Local)
+    }
+
+    /// Set the time zone
+    pub fn tz(self, tz: Tz) -> Self {
+        let precision = self.precision;
+        self.rebuild(tz, precision)
+    }
+
+    /// Set the digits of the fraction of a second
+    pub fn precision(self, precision: Precision) -> Self {
+        let tz = self.tz;
+        self.rebuild(tz, precision)
+    }
+
+    fn rebuild(mut self, tz: Tz, precision: Precision) -> Self {
+        if let Layout::Custom(_) = self.layout {
+            self.tz = tz;
+            self.precision = precision;
+            self
+        } else {
+            Self::build(self.layout, tz, precision)
+        }
+    }
+
+    fn build(layout: Layout, tz: Tz, precision: Precision) -> Self {
+        let sub = match precision {
+            Precision::Secs => "",
+            Precision::Millis => ".[subsecond digits:3]",
+            Precision::Micros => ".[subsecond digits:6]",
+            Precision::Nanos => ".[subsecond digits:9]",
+        };
+        let fmt = match (&layout, tz) {
+            (Layout::Classic, _) => classic(sub, tz.offset()),
+            (Layout::Rfc3339, Tz::Utc) => {
+                format!("[year]-[month]-[day]T[hour]:[minute]:[second]{sub}Z")
+            }
+            (Layout::Rfc3339, Tz::Local) => format!(
+                "[year]-[month]-[day]T[hour]:[minute]:[second]{sub}[offset_hour sign:mandatory]:[offset_minute]"
+            ),
+            (Layout::Custom(_), _) => unreachable!(),
+        };
+        // safe: all the built-in descriptions are valid
+        let items = parse_owned::<2>(&fmt).unwrap();
+        DateTimeFormat {
+            layout,
+            tz,
+            precision,
+            items,
+        }
+    }
+
+    /// Render a point in time
+    pub fn format(&self, t: SystemTime) -> String {
+        let nanos = match t.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_nanos() as i128,
+            Err(e) => -(e.duration().as_nanos() as i128),
+        };
+        self.format_nanos(nanos)
+    }
+
+    /// Render the current time
+    pub fn now(&self) -> String {
+        self.format(SystemTime::now())
+    }
+
+    /// Render a Unix timestamp in seconds
+    pub fn format_ts(&self, ts: i64) -> String {
+        self.format_nanos(ts as i128 * 1_000_000_000)
+    }
+
+    /// Render a Unix timestamp in milliseconds
+    pub fn format_ts_ms(&self, ts_ms: i64) -> String {
+        self.format_nanos(ts_ms as i128 * 1_000_000)
+    }
+
+    /// Render a Unix timestamp in nanoseconds.
+    ///
+    /// Timestamps outside the representable datetime range are saturated
+    /// to the minimum/maximum representable datetime instead of panicking.
+    pub fn format_nanos(&self, ts_nanos: i128) -> String {
+        // 1-day margin keeps the later `to_offset` conversion
+        // in range for any possible local offset
+        let margin = 86_400 * 1_000_000_000;
+        let min = PrimitiveDateTime::MIN.assume_utc().unix_timestamp_nanos()
+            + margin;
+        let max = PrimitiveDateTime::MAX.assume_utc().unix_timestamp_nanos()
+            - margin;
+
+        // safe: the timestamp is clamped into the representable range
+        OffsetDateTime::from_unix_timestamp_nanos(ts_nanos.clamp(min, max))
+            .unwrap()
+            .to_offset(self.tz.offset())
+            .format(&self.items)
+            .unwrap_or_default()
+    }
+
+    /// Parse a string rendered by this format into a Unix timestamp
+    /// in seconds, a missing offset means the time zone of the format
+    pub fn parse_ts(&self, s: &str) -> Result<i64> {
+        self.parse_nanos(s)
+            .c(d!())
+            .map(|n| n.div_euclid(1_000_000_000) as i64)
+    }
+
+    /// [`parse_ts`](Self::parse_ts) in milliseconds
+    pub fn parse_ts_ms(&self, s: &str) -> Result<i64> {
+        self.parse_nanos(s)
+            .c(d!())
+            .map(|n| n.div_euclid(1_000_000) as i64)
+    }
+
+    fn parse_nanos(&self, s: &str) -> Result<i128> {
+        let s = s.trim();
+        OffsetDateTime::parse(s, &self.items)
+            .or_else(|_| {
+                PrimitiveDateTime::parse(s, &self.items)
+                    .map(|t| t.assume_offset(self.tz.offset()))
+            })
+            .map(|t| t.unix_timestamp_nanos())
+            .c(d!("invalid datetime: {:?}", s))
+    }
+}
+
+// the minutes are kept only if any, so that the output round-trips
+fn classic(sub: &str, offset: UtcOffset) -> String {
+    let minute = if 0 == offset.minutes_past_hour() {
+        ""
+    } else {
+        ":[offset_minute]"
+    };
+    format!(
+        "[year]-[month]-[day] [hour]:[minute]:[second]{sub} [offset_hour sign:mandatory]{minute}"
+    )
+}
+
+/// Generate a formatted DateTime string, in the layout of
+/// [`DateTimeFormat::default`]
+///
+/// Timestamps outside the representable datetime range are saturated
+/// to the minimum/maximum representable datetime instead of panicking.
+#[inline(always)]
+pub fn gen_datetime(ts: i64) -> String {
+    DEFAULT_FORMAT.format_ts(ts)
+}
+
+/// Parse RFC 3339, ISO 8601, or the `datetime!` layout,
+/// into a Unix timestamp in seconds
+pub fn parse_datetime(s: &str) -> Result<i64> {
+    parse_any(s)
+        .c(d!())
+        .map(|n| n.div_euclid(1_000_000_000) as i64)
+}
+
+/// [`parse_datetime`] in milliseconds
+pub fn parse_datetime_ms(s: &str) -> Result<i64> {
+    parse_any(s).c(d!()).map(|n| n.div_euclid(1_000_000) as i64)
+}
+
+fn parse_any(s: &str) -> Result<i128> {
+    static CLASSIC: LazyLock<OwnedFormatItem> = LazyLock::new(|| {
+        // safe: a valid description
+        parse_owned::<2>(
+            "[year]-[month]-[day] [hour]:[minute]:[second][optional [.[subsecond]]] [offset_hour sign:mandatory][optional [:[offset_minute]]]",
+        )
+        .unwrap()
+    });
+
+    let s = s.trim();
+    OffsetDateTime::parse(s, &Rfc3339)
+        .or_else(|_| OffsetDateTime::parse(s, &Iso8601::DEFAULT))
+        .or_else(|_| OffsetDateTime::parse(s, &*CLASSIC))
+        .map(|t| t.unix_timestamp_nanos())
+        .c(d!("invalid datetime: {:?}", s))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TS: i64 = 1_700_000_000;
+
+    #[cfg(unix)]
+    #[test]
+    fn t_local_offset() {
+        let output = std::process::Command::new("date")
+            .arg("+%z")
+            .output()
+            .expect("failed to execute `date`");
+        let tz_str = std::str::from_utf8(&output.stdout).unwrap().trim();
+
+        // Parse "+0800" or "-0500" format
+        let sign: i32 = if tz_str.starts_with('-') { -1 } else { 1 };
+        let hours: i32 = tz_str[1..3].parse().unwrap();
+        let minutes: i32 = tz_str[3..5].parse().unwrap();
+        let expected = sign * (hours * 3600 + minutes * 60);
+
+        let detected = LOCAL_OFFSET.whole_seconds();
+        assert_eq!(
+            detected, expected,
+            "LOCAL_OFFSET ({}s) does not match OS timezone ({})",
+            detected, tz_str
+        );
+    }
+
+    // out-of-range timestamps saturate instead of panicking
+    #[test]
+    fn t_gen_datetime_extreme_ts() {
+        assert!(!gen_datetime(0).is_empty());
+        assert!(!gen_datetime(i64::MAX).is_empty());
+        assert!(!gen_datetime(i64::MIN).is_empty());
+        assert!(!gen_datetime(253402300799).is_empty()); // 9999-12-31 UTC
+        assert!(!gen_datetime(-377705116800).is_empty()); // 0000-01-01 UTC
+    }
+
+    #[test]
+    fn t_format() {
+        let f = DateTimeFormat::default().utc();
+        assert_eq!(f.format_ts(TS), "2023-11-14 22:13:20 +00");
+        assert_eq!(f.parse_ts("2023-11-14 22:13:20 +00").unwrap(), TS);
+
+        let f = DateTimeFormat::rfc3339().utc();
+        assert_eq!(f.format_ts(TS), "2023-11-14T22:13:20Z");
+        let ms = TS * 1000 + 7;
+        let f = f.precision(Precision::Millis);
+        assert_eq!(f.format_ts_ms(ms), "2023-11-14T22:13:20.007Z");
+        assert_eq!(f.parse_ts_ms("2023-11-14T22:13:20.007Z").unwrap(), ms);
+        let f = f.precision(Precision::Micros);
+        assert_eq!(f.format_ts_ms(ms), "2023-11-14T22:13:20.007000Z");
+        let f = f.precision(Precision::Nanos);
+        assert_eq!(
+            f.format_nanos(TS as i128 * 1_000_000_000 + 1),
+            "2023-11-14T22:13:20.000000001Z"
+        );
+        assert_eq!(f.format_ts(-1), "1969-12-31T23:59:59.000000000Z");
+        assert_eq!(f.parse_ts("1969-12-31T23:59:59.500000000Z").unwrap(), -1);
+
+        let t = UNIX_EPOCH + std::time::Duration::from_millis(1500);
+        let f = f.precision(Precision::Millis);
+        assert_eq!(f.format(t), "1970-01-01T00:00:01.500Z");
+
+        // the local one round-trips whatever the offset is
+        let f = DateTimeFormat::rfc3339().local();
+        assert_eq!(f.parse_ts(&f.format_ts(TS)).unwrap(), TS);
+        let f = DateTimeFormat::default();
+        assert_eq!(f.format_ts(TS), gen_datetime(TS));
+        assert_eq!(parse_datetime(&gen_datetime(TS)).unwrap(), TS);
+        assert!(!f.now().is_empty());
+        assert_eq!(f, DateTimeFormat::default().utc().local());
+        assert_ne!(f, DateTimeFormat::default().utc());
+
+        // any offset, whatever the local one is:
+        // whole hours keep the old `+08` form, others get the minutes
+        for (h, m, out) in [
+            (8, 0, "2023-11-15 06:13:20 +08"),
+            (-5, 0, "2023-11-14 17:13:20 -05"),
+            (0, 0, "2023-11-14 22:13:20 +00"),
+            (5, 30, "2023-11-15 03:43:20 +05:30"),
+            (5, 45, "2023-11-15 03:58:20 +05:45"),
+            (-3, -30, "2023-11-14 18:43:20 -03:30"),
+        ] {
+            let offset = UtcOffset::from_hms(h, m, 0).unwrap();
+            let items = parse_owned::<2>(&classic("", offset)).unwrap();
+            let s = OffsetDateTime::from_unix_timestamp(TS)
+                .unwrap()
+                .to_offset(offset)
+                .format(&items)
+                .unwrap();
+            assert_eq!(s, out);
+            assert_eq!(parse_datetime(&s).unwrap(), TS, "{s}");
+        }
+    }
+
+    #[test]
+    fn t_custom() {
+        let f = DateTimeFormat::custom("[year][month][day]T[hour][minute]")
+            .unwrap()
+            .utc();
+        assert_eq!(f.format_ts(TS), "20231114T2213");
+        assert_eq!(f.parse_ts("20231114T2213").unwrap(), TS - 20);
+        assert!(f.parse_ts("2023-11-14").is_err());
+
+        let f = DateTimeFormat::custom(
+            "[hour]:[minute] [offset_hour sign:mandatory][offset_minute]",
+        )
+        .unwrap();
+        // no date
+        assert!(f.parse_ts("22:13 +0000").is_err());
+
+        assert!(DateTimeFormat::custom("[year").is_err());
+        assert!(DateTimeFormat::custom("[bad]").is_err());
+    }
+
+    #[test]
+    fn t_parse_datetime() {
+        for s in [
+            "2023-11-14T22:13:20Z",
+            "2023-11-14T22:13:20+00:00",
+            "2023-11-15T06:13:20+08:00",
+            "2023-11-14t22:13:20z",
+            "20231114T221320Z",
+            "2023-11-14T17:13:20.999-05:00",
+            "2023-11-15 06:13:20 +08",
+            "2023-11-15 06:43:20 +08:30",
+            " 2023-11-14 22:13:20.5 +00 ",
+        ] {
+            assert_eq!(parse_datetime(s).unwrap(), TS, "{s}");
+        }
+        assert_eq!(
+            parse_datetime_ms("2023-11-14T22:13:20.123Z").unwrap(),
+            TS * 1000 + 123
+        );
+        for bad in ["", "2023-11-14", "2023-11-14 22:13:20", "yesterday"] {
+            assert!(parse_datetime(bad).is_err(), "{bad}");
+        }
+    }
+}
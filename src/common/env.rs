@@ -0,0 +1,514 @@
+//!
+//! # Environment variables
+//!
+//! Typed readers of environment variables. An unset(or empty) variable
+//! yields the default, a malformed one is an error naming the variable,
+//! so a typo in a deployment does not silently fall back.
+//!
+//! ```
+//! use ruc::{common::*, *};
+//! use std::time::Duration;
+//!
+//! let workers = env_parse("MY_APP_WORKERS", 4_usize)?;
+//! let timeout = env_duration("MY_APP_TIMEOUT", Duration::from_secs(30))?;
+//! let cache = env_size("MY_APP_CACHE", 64 << 20)?;
+//! let port = env_clamp("MY_APP_PORT", 8080_u16, 1024..=65535)?;
+//! # Ok::<(), Box<dyn RucError>>(())
+//! ```
+//!
+
+use crate::*;
+use std::{
+    env::{self, VarError},
+    fmt::{Debug, Display},
+    ops::RangeInclusive,
+    panic::Location,
+    str::FromStr,
+    time::Duration,
+};
+
+/// Get an environment variable or return a default value
+#[inline(always)]
+pub fn env_or(key: &str, default: &str) -> String {
+    env::var(key).unwrap_or_else(|_| default.to_owned())
+}
+
+// `None` if unset or empty
+fn env_get(key: &str) -> Result<Option<String>> {
+    match env::var(key) {
+        Ok(v) if v.trim().is_empty() => Ok(None),
+        Ok(v) => Ok(Some(v)),
+        Err(VarError::NotPresent) => Ok(None),
+        Err(e) => Err(eg!("{}: {}", key, e)),
+    }
+}
+
+// parse the value of `key` by `f`, `default` if unset
+fn env_with<T, E, F>(key: &str, default: T, f: F) -> Result<T>
+where
+    F: FnOnce(&str) -> core::result::Result<T, E>,
+    E: Display,
+{
+    match env_get(key).c(d!())? {
+        Some(v) => f(v.trim())
+            .map_err(|e| eg!("{}: invalid value {:?}, {}", key, v, e)),
+        None => Ok(default),
+    }
+}
+
+/// Parse an environment variable as `T`, `default` if unset
+pub fn env_parse<T>(key: &str, default: T) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    env_with(key, default, str::parse)
+}
+
+/// [`env_parse`], and the value must be within `range`
+pub fn env_parse_in<T>(
+    key: &str,
+    default: T,
+    range: RangeInclusive<T>,
+) -> Result<T>
+where
+    T: FromStr + PartialOrd + Debug,
+    T::Err: Display,
+{
+    let v = env_parse(key, default).c(d!())?;
+    if range.contains(&v) {
+        Ok(v)
+    } else {
+        Err(eg!("{}: {:?} is out of range {:?}", key, v, range))
+    }
+}
+
+/// [`env_parse`], a value out of `range` is clamped into it,
+/// with a warning printed at `WARN` level.
+///
+/// The warning is located at the caller(file and line),
+/// but filtered by `$RUC_LOG_LEVEL` as the module `ruc::common::env`,
+/// e.g. `ruc::common::env=error` silences it.
+#[track_caller]
+pub fn env_clamp<T>(
+    key: &str,
+    default: T,
+    range: RangeInclusive<T>,
+) -> Result<T>
+where
+    T: FromStr + PartialOrd + Debug + Clone,
+    T::Err: Display,
+{
+    let v = env_parse(key, default).c(d!())?;
+    let clamped = if &v < range.start() {
+        range.start().clone()
+    } else if range.end() < &v {
+        range.end().clone()
+    } else {
+        return Ok(v);
+    };
+    let loc = Location::caller();
+    let msg = SimpleMsg::new(
+        format!("{key}: {v:?} is out of range {range:?}, use {clamped:?}"),
+        loc.file(),
+        loc.line(),
+        loc.column(),
+    );
+    let e = SimpleError::new(msg, None);
+    crate::err::__log(&e, LogLevel::Warn, module_path!());
+    Ok(clamped)
+}
+
+/// Parse an environment variable by [`parse_duration`], `default` if unset
+pub fn env_duration(key: &str, default: Duration) -> Result<Duration> {
+    env_with(key, default, parse_duration)
+}
+
+/// Parse an environment variable by [`parse_size`], `default` if unset
+pub fn env_size(key: &str, default: u64) -> Result<u64> {
+    env_with(key, default, parse_size)
+}
+
+/// Parse a duration like `30s`, `1m30s`, `500ms` or `1.5h`,
+/// units: `ns`, `us`(`µs`), `ms`, `s`, `m`, `h`, `d`;
+/// a bare number means seconds.
+pub fn parse_duration(s: &str) -> core::result::Result<Duration, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err("empty duration".to_owned());
+    }
+    if let Ok(secs) = s.parse::<f64>() {
+        return to_duration(secs, s);
+    }
+
+    let mut total = 0f64;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let num_end = rest
+            .find(|c: char| !(c.is_ascii_digit() || '.' == c))
+            .unwrap_or(rest.len());
+        let unit_end = rest[num_end..]
+            .find(|c: char| c.is_ascii_digit() || '.' == c)
+            .map_or(rest.len(), |i| num_end + i);
+        let num = rest[..num_end]
+            .parse::<f64>()
+            .map_err(|_| format!("invalid duration: {s:?}"))?;
+        let unit = match rest[num_end..unit_end].trim() {
+            "ns" => 1e-9,
+            "us" | "µs" => 1e-6,
+            "ms" => 1e-3,
+            "s" | "sec" => 1.0,
+            "m" | "min" => 60.0,
+            "h" => 3600.0,
+            "d" => 86400.0,
+            u => return Err(format!("unknown duration unit {u:?} in {s:?}")),
+        };
+        total += num * unit;
+        rest = rest[unit_end..].trim_start();
+    }
+    to_duration(total, s)
+}
+
+fn to_duration(secs: f64, s: &str) -> core::result::Result<Duration, String> {
+    Duration::try_from_secs_f64(secs)
+        .map_err(|_| format!("invalid duration: {s:?}"))
+}
+
+/// Parse a size in bytes like `512`, `64KiB`, `10MB` or `1.5G`,
+/// `K`/`M`/`G`/`T` alone and with `iB` are powers of 1024,
+/// with `B` they are powers of 1000, case-insensitive.
+pub fn parse_size(s: &str) -> core::result::Result<u64, String> {
+    let s = s.trim();
+    let num_end = s
+        .find(|c: char| !(c.is_ascii_digit() || '.' == c))
+        .unwrap_or(s.len());
+    let num = s[..num_end]
+        .parse::<f64>()
+        .map_err(|_| format!("invalid size: {s:?}"))?;
+    let unit = s[num_end..].trim().to_ascii_lowercase();
+    let (exp, base) = match unit.as_str() {
+        "" | "b" => (0, 1024f64),
+        "k" | "kib" => (1, 1024.0),
+        "m" | "mib" => (2, 1024.0),
+        "g" | "gib" => (3, 1024.0),
+        "t" | "tib" => (4, 1024.0),
+        "kb" => (1, 1000.0),
+        "mb" => (2, 1000.0),
+        "gb" => (3, 1000.0),
+        "tb" => (4, 1000.0),
+        u => return Err(format!("unknown size unit {u:?} in {s:?}")),
+    };
+    let n = (num * base.powi(exp)).round();
+    if n.is_finite() && n <= u64::MAX as f64 {
+        Ok(n as u64)
+    } else {
+        Err(format!("size out of range: {s:?}"))
+    }
+}
+
+/// Load all the variables named `{prefix}_{FIELD}` into a struct,
+/// the field names are the lower-cased remainders,
+/// e.g. `APP_DB_URL` sets the field `db_url` with the prefix `APP`.
+///
+/// Values are parsed by the field types, `Option` fields are `None`
+/// if unset or empty, sequence fields are comma-separated.
+#[cfg(feature = "serde")]
+pub fn env_load<T: serde::de::DeserializeOwned>(prefix: &str) -> Result<T> {
+    let prefix = format!("{}_", prefix.trim_end_matches('_'));
+    let vars = env::vars_os()
+        .filter_map(|(k, v)| {
+            let k = k.into_string().ok()?;
+            let field = k.strip_prefix(&prefix)?.to_ascii_lowercase();
+            let v = v.into_string().ok()?;
+            Some((field, de::Value { key: k, v }))
+        })
+        .collect::<Vec<_>>();
+    let map =
+        serde::de::value::MapDeserializer::<_, serde::de::value::Error>::new(
+            vars.into_iter(),
+        );
+    T::deserialize(map).c(d!("load env with prefix {}", prefix))
+}
+
+#[cfg(feature = "serde")]
+mod de {
+    use serde::de::{
+        self, Deserializer, IntoDeserializer, Visitor,
+        value::{Error, SeqDeserializer},
+    };
+
+    // the value of one variable, parsed on demand by the field type
+    pub(super) struct Value {
+        pub(super) key: String,
+        pub(super) v: String,
+    }
+
+    impl Value {
+        fn parse<T>(&self) -> Result<T, Error>
+        where
+            T: core::str::FromStr,
+            T::Err: core::fmt::Display,
+        {
+            self.v.trim().parse().map_err(|e| {
+                de::Error::custom(format!(
+                    "{}: invalid value {:?}, {}",
+                    self.key, self.v, e
+                ))
+            })
+        }
+    }
+
+    impl<'de> IntoDeserializer<'de, Error> for Value {
+        type Deserializer = Self;
+
+        fn into_deserializer(self) -> Self {
+            self
+        }
+    }
+
+    macro_rules! parse_as {
+        ($($method: ident => $visit: ident),* $(,)?) => {$(
+            fn $method<V: Visitor<'de>>(self, v: V) -> Result<V::Value, Error> {
+                v.$visit(self.parse()?)
+            }
+        )*};
+    }
+
+    impl<'de> Deserializer<'de> for Value {
+        type Error = Error;
+
+        fn deserialize_any<V: Visitor<'de>>(
+            self,
+            v: V,
+        ) -> Result<V::Value, Error> {
+            v.visit_string(self.v)
+        }
+
+        parse_as! {
+            deserialize_bool => visit_bool,
+            deserialize_i8 => visit_i8,
+            deserialize_i16 => visit_i16,
+            deserialize_i32 => visit_i32,
+            deserialize_i64 => visit_i64,
+            deserialize_i128 => visit_i128,
+            deserialize_u8 => visit_u8,
+            deserialize_u16 => visit_u16,
+            deserialize_u32 => visit_u32,
+            deserialize_u64 => visit_u64,
+            deserialize_u128 => visit_u128,
+            deserialize_f32 => visit_f32,
+            deserialize_f64 => visit_f64,
+            deserialize_char => visit_char,
+        }
+
+        fn deserialize_option<V: Visitor<'de>>(
+            self,
+            v: V,
+        ) -> Result<V::Value, Error> {
+            if self.v.trim().is_empty() {
+                v.visit_none()
+            } else {
+                v.visit_some(self)
+            }
+        }
+
+        fn deserialize_newtype_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            v: V,
+        ) -> Result<V::Value, Error> {
+            v.visit_newtype_struct(self)
+        }
+
+        fn deserialize_seq<V: Visitor<'de>>(
+            self,
+            v: V,
+        ) -> Result<V::Value, Error> {
+            let items = self
+                .v
+                .split(',')
+                .map(str::trim)
+                .filter(|i| !i.is_empty())
+                .map(|i| Value {
+                    key: self.key.clone(),
+                    v: i.to_owned(),
+                })
+                .collect::<Vec<_>>();
+            v.visit_seq(SeqDeserializer::new(items.into_iter()))
+        }
+
+        fn deserialize_enum<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            _variants: &'static [&'static str],
+            v: V,
+        ) -> Result<V::Value, Error> {
+            v.visit_enum(self.v.trim().to_owned().into_deserializer())
+        }
+
+        serde::forward_to_deserialize_any! {
+            str string bytes byte_buf unit unit_struct
+            tuple tuple_struct map struct identifier ignored_any
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{Mutex, MutexGuard};
+
+    // The environment is shared by the whole process,
+    // the tests touching it hold this lock from start to end.
+    static ENV: Mutex<()> = Mutex::new(());
+
+    fn lock() -> MutexGuard<'static, ()> {
+        ENV.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn set(_guard: &MutexGuard<'static, ()>, key: &str, v: &str) {
+        // SAFETY: the tests access the environment only under `ENV`,
+        // the other readers of this crate go through `std::env`,
+        // which is synchronized with `set_var`
+        unsafe { env::set_var(key, v) };
+    }
+
+    fn unset(_guard: &MutexGuard<'static, ()>, key: &str) {
+        // SAFETY: same as `set`
+        unsafe { env::remove_var(key) };
+    }
+
+    #[test]
+    fn t_env_or() {
+        let g = lock();
+        set(&g, "RUC_TEST_ENV_OR", "hello");
+        assert_eq!(env_or("RUC_TEST_ENV_OR", "default"), "hello");
+        unset(&g, "RUC_TEST_ENV_OR");
+        assert_eq!(env_or("RUC_TEST_ENV_OR_NONEXIST", "default"), "default");
+    }
+
+    #[test]
+    fn t_env_parse() {
+        let g = lock();
+        assert_eq!(env_parse("RUC_TEST_PARSE_UNSET", 7u32).unwrap(), 7);
+        set(&g, "RUC_TEST_PARSE_EMPTY", " ");
+        assert_eq!(env_parse("RUC_TEST_PARSE_EMPTY", 7u32).unwrap(), 7);
+        set(&g, "RUC_TEST_PARSE", " 42 ");
+        assert_eq!(env_parse("RUC_TEST_PARSE", 7u32).unwrap(), 42);
+        set(&g, "RUC_TEST_PARSE_BAD", "4x2");
+        let e = env_parse("RUC_TEST_PARSE_BAD", 7u32).unwrap_err();
+        assert!(
+            e.get_top_msg()
+                .starts_with("RUC_TEST_PARSE_BAD: invalid value \"4x2\"")
+        );
+
+        assert_eq!(env_parse_in("RUC_TEST_PARSE", 7u32, 1..=100).unwrap(), 42);
+        let e = env_parse_in("RUC_TEST_PARSE", 7u32, 1..=10).unwrap_err();
+        assert_eq!(
+            e.get_top_msg(),
+            "RUC_TEST_PARSE: 42 is out of range 1..=10"
+        );
+
+        assert_eq!(env_clamp("RUC_TEST_PARSE", 7u32, 1..=10).unwrap(), 10);
+        assert_eq!(env_clamp("RUC_TEST_PARSE", 7u32, 50..=60).unwrap(), 50);
+        assert_eq!(env_clamp("RUC_TEST_PARSE", 7u32, 1..=60).unwrap(), 42);
+        assert!(env_clamp("RUC_TEST_PARSE_BAD", 7u32, 1..=60).is_err());
+    }
+
+    #[test]
+    fn t_parse_duration() {
+        let g = lock();
+        let ms = Duration::from_millis;
+        assert_eq!(parse_duration("3").unwrap(), ms(3000));
+        assert_eq!(parse_duration("0.5").unwrap(), ms(500));
+        assert_eq!(parse_duration("30s").unwrap(), ms(30_000));
+        assert_eq!(parse_duration("500ms").unwrap(), ms(500));
+        assert_eq!(parse_duration("1m30s").unwrap(), ms(90_000));
+        assert_eq!(parse_duration("1h 1m").unwrap(), ms(3_660_000));
+        assert_eq!(parse_duration("1.5h").unwrap(), ms(5_400_000));
+        assert_eq!(parse_duration("2d").unwrap(), ms(172_800_000));
+        assert_eq!(parse_duration("10us").unwrap(), Duration::from_micros(10));
+        assert_eq!(parse_duration("7ns").unwrap(), Duration::from_nanos(7));
+        for bad in ["", "s", "10x", "-1s", "1..5s", "1e999"] {
+            assert!(parse_duration(bad).is_err(), "{bad}");
+        }
+
+        set(&g, "RUC_TEST_DURATION", "2m");
+        let d = env_duration("RUC_TEST_DURATION", ms(1)).unwrap();
+        assert_eq!(d, ms(120_000));
+    }
+
+    #[test]
+    fn t_parse_size() {
+        let g = lock();
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("512B").unwrap(), 512);
+        assert_eq!(parse_size("64KiB").unwrap(), 64 << 10);
+        assert_eq!(parse_size("10MiB").unwrap(), 10 << 20);
+        assert_eq!(parse_size("10mb").unwrap(), 10_000_000);
+        assert_eq!(parse_size("1.5G").unwrap(), 3 << 29);
+        assert_eq!(parse_size("2 TB").unwrap(), 2_000_000_000_000);
+        for bad in ["", "MiB", "10XB", "-1", "99999999999T"] {
+            assert!(parse_size(bad).is_err(), "{bad}");
+        }
+
+        set(&g, "RUC_TEST_SIZE", "1k");
+        assert_eq!(env_size("RUC_TEST_SIZE", 0).unwrap(), 1024);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn t_env_load() {
+        let g = lock();
+
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Mode {
+            Fast,
+            Safe,
+        }
+
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Cfg {
+            db_url: String,
+            workers: u16,
+            verbose: bool,
+            ratio: f64,
+            mode: Mode,
+            peers: Vec<String>,
+            token: Option<String>,
+            #[serde(default)]
+            retries: u8,
+        }
+
+        set(&g, "RUC_TEST_LOAD_DB_URL", "pg://x");
+        set(&g, "RUC_TEST_LOAD_WORKERS", "8");
+        set(&g, "RUC_TEST_LOAD_VERBOSE", "true");
+        set(&g, "RUC_TEST_LOAD_RATIO", "0.5");
+        set(&g, "RUC_TEST_LOAD_MODE", "safe");
+        set(&g, "RUC_TEST_LOAD_PEERS", "a:1, b:2,");
+        set(&g, "RUC_TEST_LOAD_TOKEN", "");
+        let cfg = env_load::<Cfg>("RUC_TEST_LOAD").unwrap();
+        assert_eq!(
+            cfg,
+            Cfg {
+                db_url: "pg://x".to_owned(),
+                workers: 8,
+                verbose: true,
+                ratio: 0.5,
+                mode: Mode::Safe,
+                peers: vec!["a:1".to_owned(), "b:2".to_owned()],
+                token: None,
+                retries: 0,
+            }
+        );
+
+        set(&g, "RUC_TEST_LOAD_WORKERS", "many");
+        let e = env_load::<Cfg>("RUC_TEST_LOAD_").unwrap_err();
+        assert!(
+            e.stringify_chain(None)
+                .contains("RUC_TEST_LOAD_WORKERS: invalid value \"many\"")
+        );
+        assert!(env_load::<Cfg>("RUC_TEST_LOAD_NONE").is_err());
+    }
+}
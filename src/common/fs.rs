@@ -0,0 +1,269 @@
+//!
+//! # File utils
+//!
+//! Thin wrappers of `std::fs`, every error is chained
+//! with the path of the file.
+//!
+//! [`write_file_atomic`] never leaves a truncated file behind:
+//! the content goes to a temporary file in the same directory,
+//! which is synced, then renamed over the target.
+//!
+
+use crate::*;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// Read a file to string, wrapping errors with ruc error chain
+#[inline(always)]
+pub fn read_file<P: AsRef<Path>>(path: P) -> Result<String> {
+    let path = path.as_ref();
+    fs::read_to_string(path).c(d!("read {}", path.display()))
+}
+
+/// Read a file to bytes, wrapping errors with ruc error chain
+#[inline(always)]
+pub fn read_file_bytes<P: AsRef<Path>>(path: P) -> Result<Vec<u8>> {
+    let path = path.as_ref();
+    fs::read(path).c(d!("read {}", path.display()))
+}
+
+/// Read all the lines of a file, without the line endings
+pub fn read_lines<P: AsRef<Path>>(path: P) -> Result<Vec<String>> {
+    let path = path.as_ref();
+    let f = File::open(path).c(d!("open {}", path.display()))?;
+    BufReader::new(f)
+        .lines()
+        .enumerate()
+        .map(|(i, l)| {
+            l.c_with(|| d!("read {}, line {}", path.display(), i + 1))
+        })
+        .collect()
+}
+
+/// Write contents to a file, wrapping errors with ruc error chain
+#[inline(always)]
+pub fn write_file<P: AsRef<Path>>(
+    path: P,
+    content: impl AsRef<[u8]>,
+) -> Result<()> {
+    let path = path.as_ref();
+    fs::write(path, content).c(d!("write {}", path.display()))
+}
+
+/// Append contents to a file, create it if not exists
+pub fn append_file<P: AsRef<Path>>(
+    path: P,
+    content: impl AsRef<[u8]>,
+) -> Result<()> {
+    let path = path.as_ref();
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut f| f.write_all(content.as_ref()))
+        .c(d!("append {}", path.display()))
+}
+
+/// Options of [`write_file_atomic_with`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+    mode: Option<u32>,
+    owner: Option<(Option<u32>, Option<u32>)>,
+}
+
+impl WriteOptions {
+    /// Keep the permissions of the existing file, if any
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the permission bits, e.g. `0o600`,
+    /// not affected by the umask
+    #[cfg(unix)]
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Set the owner and/or the group, `None` leaves it unchanged
+    #[cfg(unix)]
+    pub fn owner(mut self, uid: Option<u32>, gid: Option<u32>) -> Self {
+        self.owner = Some((uid, gid));
+        self
+    }
+}
+
+/// Replace the contents of a file atomically,
+/// readers see either the old contents or the new ones, never a mix,
+/// even if the process or the machine crashes in the middle.
+#[inline(always)]
+pub fn write_file_atomic<P: AsRef<Path>>(
+    path: P,
+    content: impl AsRef<[u8]>,
+) -> Result<()> {
+    write_file_atomic_with(path, content, &WriteOptions::default())
+}
+
+/// [`write_file_atomic`] with permissions and ownership
+pub fn write_file_atomic_with<P: AsRef<Path>>(
+    path: P,
+    content: impl AsRef<[u8]>,
+    opts: &WriteOptions,
+) -> Result<()> {
+    static SEQ: AtomicU64 = AtomicU64::new(0);
+
+    let path = path.as_ref();
+    let name = path
+        .file_name()
+        .c(d!("not a file path: {}", path.display()))?;
+    let dir = match path.parent() {
+        Some(d) if !d.as_os_str().is_empty() => d,
+        _ => Path::new("."),
+    };
+    let tmp = dir.join(format!(
+        ".{}.tmp.{}.{}",
+        name.to_string_lossy(),
+        std::process::id(),
+        SEQ.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let res = write_tmp(&tmp, path, content.as_ref(), opts)
+        .and_then(|_| {
+            fs::rename(&tmp, path).c(d!(
+                "rename {} to {}",
+                tmp.display(),
+                path.display()
+            ))
+        })
+        .and_then(|_| sync_dir(dir));
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    res.c(d!("write {}", path.display()))
+}
+
+fn write_tmp(
+    tmp: &Path,
+    target: &Path,
+    content: &[u8],
+    opts: &WriteOptions,
+) -> Result<()> {
+    let mut f = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(tmp)
+        .c(d!("create {}", tmp.display()))?;
+    f.write_all(content).c(d!("write {}", tmp.display()))?;
+
+    let perm = match opts.mode {
+        #[cfg(unix)]
+        Some(mode) => {
+            use std::os::unix::fs::PermissionsExt;
+            Some(fs::Permissions::from_mode(mode))
+        }
+        #[cfg(not(unix))]
+        Some(_) => None,
+        None => fs::metadata(target).ok().map(|m| m.permissions()),
+    };
+    if let Some(perm) = perm {
+        f.set_permissions(perm)
+            .c(d!("set permissions of {}", tmp.display()))?;
+    }
+
+    #[cfg(unix)]
+    if let Some((uid, gid)) = opts.owner {
+        std::os::unix::fs::fchown(&f, uid, gid)
+            .c(d!("set owner of {}", tmp.display()))?;
+    }
+
+    f.sync_all().c(d!("sync {}", tmp.display()))
+}
+
+// Persist the rename itself, only meaningful(and possible) on unix
+fn sync_dir(dir: &Path) -> Result<()> {
+    #[cfg(unix)]
+    File::open(dir)
+        .and_then(|d| d.sync_all())
+        .c(d!("sync {}", dir.display()))?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tmp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "ruc_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn t_file_utils() {
+        let dir = std::env::temp_dir();
+        let path = dir.join("ruc_test_file_utils.txt");
+        write_file(&path, "hello ruc").unwrap();
+        let content = read_file(&path).unwrap();
+        assert_eq!(content, "hello ruc");
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn t_append_read_lines() {
+        let dir = tmp_dir("append");
+        let path = dir.join("log.txt");
+        append_file(&path, "a\n").unwrap();
+        append_file(&path, b"b\r\nc").unwrap();
+        assert_eq!(read_lines(&path).unwrap(), ["a", "b", "c"]);
+        assert_eq!(read_file_bytes(&path).unwrap(), b"a\nb\r\nc");
+
+        let e = read_lines(dir.join("nonexistent")).unwrap_err();
+        assert!(e.get_top_msg().contains("nonexistent"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn t_write_file_atomic() {
+        let dir = tmp_dir("atomic");
+        let path = dir.join("cfg.toml");
+        write_file_atomic(&path, "v = 1").unwrap();
+        write_file_atomic(&path, "v = 2").unwrap();
+        assert_eq!(read_file(&path).unwrap(), "v = 2");
+        // no temporary file left
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let mode = |p: &Path| fs::metadata(p).unwrap().mode() & 0o777;
+
+            let opts = WriteOptions::new().mode(0o640);
+            write_file_atomic_with(&path, "v = 3", &opts).unwrap();
+            assert_eq!(mode(&path), 0o640);
+            // kept if not set
+            write_file_atomic(&path, "v = 4").unwrap();
+            assert_eq!(mode(&path), 0o640);
+
+            let uid = fs::metadata(&path).unwrap().uid();
+            let opts = WriteOptions::new().owner(Some(uid), None);
+            write_file_atomic_with(&path, "v = 5", &opts).unwrap();
+            assert_eq!(fs::metadata(&path).unwrap().uid(), uid);
+        }
+
+        let e = write_file_atomic(dir.join("no/such/dir/f"), "").unwrap_err();
+        assert!(e.get_top_msg().contains("no/such/dir/f"));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,448 @@
+//!
+//! # Retry policies
+//!
+//! A [`RetryPolicy`] decides how many times, how long to wait between
+//! attempts and which errors are worth another try. Every failed attempt
+//! is kept: when the policy gives up, the returned error holds all of them
+//! as the branches of a [`MultiError`].
+//!
+//! ```
+//! use ruc::{common::*, *};
+//! use std::time::Duration;
+//!
+//! let policy = RetryPolicy::new(5)
+//!     .backoff(Backoff::Exponential)
+//!     .base_delay(Duration::from_millis(1))
+//!     .max_delay(Duration::from_millis(4))
+//!     .jitter(Jitter::Full)
+//!     .retry_if(|e| e.code() != Some(404));
+//!
+//! let mut n = 0;
+//! let e = policy
+//!     .run(|| -> Result<()> {
+//!         n += 1;
+//!         if n < 3 {
+//!             Err(eg!(code = 503, "busy"))
+//!         } else {
+//!             Err(eg!(code = 404, "gone"))
+//!         }
+//!     })
+//!     .unwrap_err();
+//! assert_eq!(n, 3);
+//! assert_eq!(e.branches().len(), 3);
+//! ```
+//!
+
+use crate::err::{MultiError, Result, RucError, SimpleMsg};
+use std::{
+    fmt,
+    panic::Location,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// How the delay grows with the number of failed attempts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backoff {
+    /// always `base_delay`
+    #[default]
+    Fixed,
+    /// `base_delay * n` after the n-th failure
+    Linear,
+    /// `base_delay * 2^(n-1)` after the n-th failure
+    Exponential,
+}
+
+/// Randomization applied to the delays,
+/// so that many clients failing together do not retry together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Jitter {
+    /// use the delay as-is
+    #[default]
+    None,
+    /// a random delay in `[0, delay]`
+    Full,
+    /// a random delay in `[base_delay, previous delay * 3]`,
+    /// the backoff strategy is ignored, only the `max_delay` cap applies
+    Decorrelated,
+}
+
+type Predicate = Arc<dyn Fn(&dyn RucError) -> bool + Send + Sync>;
+
+/// A reusable description of how to retry a fallible operation
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: usize,
+    base_delay: Duration,
+    max_delay: Option<Duration>,
+    backoff: Backoff,
+    jitter: Jitter,
+    deadline: Option<Duration>,
+    retryable: Option<Predicate>,
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("base_delay", &self.base_delay)
+            .field("max_delay", &self.max_delay)
+            .field("backoff", &self.backoff)
+            .field("jitter", &self.jitter)
+            .field("deadline", &self.deadline)
+            .field("retryable", &self.retryable.is_some())
+            .finish()
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl RetryPolicy {
+    /// At most `max_attempts`(at least 1) attempts, without any delay,
+    /// every error is retryable
+    pub fn new(max_attempts: usize) -> Self {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            base_delay: Duration::ZERO,
+            max_delay: None,
+            backoff: Backoff::Fixed,
+            jitter: Jitter::None,
+            deadline: None,
+            retryable: None,
+        }
+    }
+
+    /// Set the maximum number of attempts, at least 1
+    pub fn max_attempts(mut self, n: usize) -> Self {
+        self.max_attempts = n.max(1);
+        self
+    }
+
+    /// Set the delay after the first failure
+    pub fn base_delay(mut self, d: Duration) -> Self {
+        self.base_delay = d;
+        self
+    }
+
+    /// Cap every delay
+    pub fn max_delay(mut self, d: Duration) -> Self {
+        self.max_delay = Some(d);
+        self
+    }
+
+    /// Set the backoff strategy
+    pub fn backoff(mut self, b: Backoff) -> Self {
+        self.backoff = b;
+        self
+    }
+
+    /// Set the jitter strategy
+    pub fn jitter(mut self, j: Jitter) -> Self {
+        self.jitter = j;
+        self
+    }
+
+    /// Give up once `d` has elapsed since the first attempt,
+    /// a delay that would end beyond it is not waited for
+    pub fn deadline(mut self, d: Duration) -> Self {
+        self.deadline = Some(d);
+        self
+    }
+
+    /// Only retry the errors for which `f` returns `true`,
+    /// any other error stops the retrying immediately
+    pub fn retry_if<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&dyn RucError) -> bool + Send + Sync + 'static,
+    {
+        self.retryable = Some(Arc::new(f));
+        self
+    }
+
+    /// Run `f` until it succeeds or the policy gives up,
+    /// the delays are spent in `std::thread::sleep`.
+    #[track_caller]
+    pub fn run<T, F>(&self, mut f: F) -> Result<T>
+    where
+        F: FnMut() -> Result<T>,
+    {
+        let mut state = RetryState::new(self, Location::caller());
+        loop {
+            match f() {
+                Ok(v) => return Ok(v),
+                Err(e) => match state.failed(e) {
+                    Ok(delay) => std::thread::sleep(delay),
+                    Err(e) => return Err(e),
+                },
+            }
+        }
+    }
+
+    // the delay before the next attempt, `n` failures so far
+    fn delay(&self, n: usize, prev: Duration, rng: &mut XorShift) -> Duration {
+        let delay = match self.jitter {
+            Jitter::Decorrelated => {
+                let hi = prev.saturating_mul(3).max(self.base_delay);
+                rng.between(self.base_delay, hi)
+            }
+            Jitter::None => self.raw_delay(n),
+            Jitter::Full => rng.between(Duration::ZERO, self.raw_delay(n)),
+        };
+        match self.max_delay {
+            Some(cap) => delay.min(cap),
+            None => delay,
+        }
+    }
+
+    fn raw_delay(&self, n: usize) -> Duration {
+        let n = u32::try_from(n).unwrap_or(u32::MAX).max(1);
+        let delay = match self.backoff {
+            Backoff::Fixed => self.base_delay,
+            Backoff::Linear => self.base_delay.saturating_mul(n),
+            Backoff::Exponential => {
+                self.base_delay.saturating_mul(2u32.saturating_pow(n - 1))
+            }
+        };
+        match self.max_delay {
+            Some(cap) => delay.min(cap),
+            None => delay,
+        }
+    }
+}
+
+/// The progress of one retried operation,
+/// independent of how the delays are waited for
+pub(crate) struct RetryState<'a> {
+    policy: &'a RetryPolicy,
+    loc: &'static Location<'static>,
+    start: Instant,
+    prev_delay: Duration,
+    rng: XorShift,
+    errors: Vec<Box<dyn RucError>>,
+}
+
+impl<'a> RetryState<'a> {
+    pub(crate) fn new(
+        policy: &'a RetryPolicy,
+        loc: &'static Location<'static>,
+    ) -> Self {
+        RetryState {
+            policy,
+            loc,
+            start: Instant::now(),
+            prev_delay: policy.base_delay,
+            rng: XorShift::new(),
+            errors: vec![],
+        }
+    }
+
+    /// Record a failed attempt, return the delay before the next one,
+    /// or the final error if the policy gives up
+    pub(crate) fn failed(&mut self, e: Box<dyn RucError>) -> Result<Duration> {
+        let retryable = self.policy.retryable.as_ref().is_none_or(|f| f(&*e));
+        self.errors.push(e);
+        let n = self.errors.len();
+
+        let reason = if !retryable {
+            "non-retryable error"
+        } else if self.policy.max_attempts <= n {
+            "no attempts left"
+        } else {
+            let delay = self.policy.delay(n, self.prev_delay, &mut self.rng);
+            match self.policy.deadline {
+                Some(d) if d < self.start.elapsed().saturating_add(delay) => {
+                    "deadline exceeded"
+                }
+                _ => {
+                    self.prev_delay = delay;
+                    return Ok(delay);
+                }
+            }
+        };
+
+        let msg = format!("retry failed after {n} attempts: {reason}");
+        let msg = SimpleMsg::new(
+            msg,
+            self.loc.file(),
+            self.loc.line(),
+            self.loc.column(),
+        );
+        let errors = std::mem::take(&mut self.errors);
+        Err(MultiError::new(msg, errors).into())
+    }
+}
+
+// A tiny xorshift64 generator, good enough for jitter
+struct XorShift(u64);
+
+impl XorShift {
+    fn new() -> Self {
+        static SEQ: AtomicU64 = AtomicU64::new(0);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        let seq = SEQ.fetch_add(1, Ordering::Relaxed);
+        // never zero, or it would stay zero forever
+        XorShift((nanos ^ seq.wrapping_mul(0x9E37_79B9_7F4A_7C15)) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    // uniformly in `[lo, hi]`, `lo` if `hi < lo`
+    fn between(&mut self, lo: Duration, hi: Duration) -> Duration {
+        let lo_ns = u64::try_from(lo.as_nanos()).unwrap_or(u64::MAX);
+        let hi_ns = u64::try_from(hi.as_nanos()).unwrap_or(u64::MAX);
+        if hi_ns <= lo_ns {
+            return lo;
+        }
+        let span = hi_ns - lo_ns;
+        let offset = match span.checked_add(1) {
+            Some(m) => self.next() % m,
+            None => self.next(),
+        };
+        Duration::from_nanos(lo_ns + offset)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn t_backoff() {
+        let mut rng = XorShift::new();
+        let p = RetryPolicy::new(10).base_delay(MS * 10);
+        let delays = |p: &RetryPolicy, rng: &mut XorShift| {
+            (1..=5).map(|n| p.delay(n, MS, rng)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(delays(&p, &mut rng), [MS * 10; 5]);
+
+        let p = p.backoff(Backoff::Linear);
+        assert_eq!(
+            delays(&p, &mut rng),
+            [MS * 10, MS * 20, MS * 30, MS * 40, MS * 50]
+        );
+
+        let p = p.backoff(Backoff::Exponential).max_delay(MS * 100);
+        assert_eq!(
+            delays(&p, &mut rng),
+            [MS * 10, MS * 20, MS * 40, MS * 80, MS * 100]
+        );
+        assert_eq!(p.delay(usize::MAX, MS, &mut rng), MS * 100);
+
+        let p = p.jitter(Jitter::Full);
+        for (n, d) in delays(&p, &mut rng).into_iter().enumerate() {
+            assert!(d <= p.raw_delay(n + 1));
+        }
+
+        let p = p.jitter(Jitter::Decorrelated);
+        for prev in [MS, MS * 10, MS * 30, MS * 1000] {
+            let d = p.delay(1, prev, &mut rng);
+            assert!(MS * 10 <= d && d <= MS * 100);
+            assert!(d <= (prev * 3).max(MS * 10));
+        }
+    }
+
+    #[test]
+    fn t_rng() {
+        let mut rng = XorShift::new();
+        assert_eq!(rng.between(MS * 5, MS), MS * 5);
+        assert_eq!(rng.between(MS, MS), MS);
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..1000 {
+            let d = rng.between(MS, MS * 4);
+            assert!(MS <= d && d <= MS * 4);
+            seen.insert(d);
+        }
+        assert!(100 < seen.len());
+    }
+
+    #[test]
+    fn t_run() {
+        let p = RetryPolicy::new(4);
+
+        let mut n = 0;
+        let v = p.run(|| {
+            n += 1;
+            if n < 3 {
+                Err(eg!("flaky {}", n))
+            } else {
+                Ok(n)
+            }
+        });
+        assert_eq!(v.unwrap(), 3);
+
+        let e = p.run(|| -> Result<()> { Err(eg!("down")) }).unwrap_err();
+        assert_eq!(
+            e.get_top_msg(),
+            "retry failed after 4 attempts: no attempts left"
+        );
+        assert_eq!(e.branches().len(), 4);
+        assert_eq!(e.location().unwrap().0, file!());
+
+        let p = p.retry_if(|e| e.code() == Some(503));
+        let mut n = 0;
+        let e = p
+            .run(|| -> Result<()> {
+                n += 1;
+                if n < 2 {
+                    Err(eg!(code = 503, "busy"))
+                } else {
+                    Err(eg!(code = 400, "bad request"))
+                }
+            })
+            .unwrap_err();
+        assert_eq!(n, 2);
+        assert!(e.get_top_msg().ends_with("non-retryable error"));
+        assert_eq!(e.branches()[1].code(), Some(400));
+    }
+
+    #[test]
+    fn t_lowest() {
+        use core::{any::TypeId, num::ParseIntError};
+
+        let e = crate::common::retry(2, 0, || "x".parse::<u8>()).unwrap_err();
+        // the `lowest_*` API agree on the summary level
+        assert!(e.get_lowest_msg().starts_with("retry failed after 2"));
+        assert_eq!(e.lowest_type_id(), TypeId::of::<MultiError>());
+        assert!(e.get_lowest_err().type_name().ends_with("MultiError"));
+        let parse_err = "".parse::<u8>().unwrap_err();
+        assert!(!e.lowest_is_type(&parse_err));
+        assert!(e.downcast_ref::<ParseIntError>().is_none());
+
+        // the attempts are branches
+        assert!(e.contains_type(&parse_err));
+        assert!(e.find::<ParseIntError>().is_some());
+        let last = e.branches().last().unwrap();
+        assert!(last.lowest_is_type(&parse_err));
+        assert!(last.downcast_ref::<ParseIntError>().is_some());
+    }
+
+    #[test]
+    fn t_deadline() {
+        let p = RetryPolicy::new(100).base_delay(MS * 20).deadline(MS * 50);
+        let e = p.run(|| -> Result<()> { Err(eg!()) }).unwrap_err();
+        assert!(e.get_top_msg().ends_with("deadline exceeded"));
+        // 3 unless the sleeps overshoot a lot
+        assert!((2..=3).contains(&e.branches().len()));
+    }
+}
@@ -0,0 +1,256 @@
+//!
+//! # Log level
+//!
+//! The filter is initialized from `RUC_LOG_LEVEL` at the first use,
+//! and can be replaced at runtime by [`set_log_level`]/[`set_log_filter`].
+//!
+//! The syntax follows `RUST_LOG`: a comma-separated list of
+//! `[module::path=]level` directives, e.g. `warn,my_app::net=debug`;
+//! the longest matching module path wins,
+//! a bare level sets the default(`INFO` if absent).
+//!
+
+use alloc::{borrow::ToOwned, format, string::String, vec, vec::Vec};
+use core::str::FromStr;
+#[cfg(feature = "std")]
+use std::sync::{LazyLock, RwLock};
+
+#[cfg(feature = "std")]
+static FILTER: LazyLock<RwLock<LogFilter>> = LazyLock::new(|| {
+    // a malformed spec is reported, its valid directives still apply
+    let f = crate::common::env_parse("RUC_LOG_LEVEL", LogFilter::default())
+        .unwrap_or_else(|e| {
+            e.print_level(LogLevel::Warn);
+            std::env::var("RUC_LOG_LEVEL")
+                .map(|spec| LogFilter::parse(&spec))
+                .unwrap_or_default()
+        });
+    RwLock::new(f)
+});
+
+/// Severity of a log entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    /// `trace!`
+    Trace,
+    /// `debug!`
+    Debug,
+    /// `info!` and its variants
+    Info,
+    /// `warn!`
+    Warn,
+    /// `pnk!`, `die!` and [`RucError::print`](crate::RucError::print)
+    Error,
+}
+
+impl LogLevel {
+    /// Upper-case name of the level, used as the log prefix
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Trace => "TRACE",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = ();
+
+    /// case-insensitive
+    fn from_str(s: &str) -> core::result::Result<Self, ()> {
+        match s.trim().to_ascii_uppercase().as_str() {
+            "TRACE" => Ok(LogLevel::Trace),
+            "DEBUG" => Ok(LogLevel::Debug),
+            "INFO" => Ok(LogLevel::Info),
+            "WARN" | "WARNING" => Ok(LogLevel::Warn),
+            "ERROR" => Ok(LogLevel::Error),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Default level plus per-module levels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFilter {
+    default: LogLevel,
+    // sorted by the length of module paths, longest first
+    modules: Vec<(String, LogLevel)>,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        LogFilter {
+            default: LogLevel::Info,
+            modules: vec![],
+        }
+    }
+}
+
+impl LogFilter {
+    /// Parse a `RUST_LOG`-like spec, invalid directives are ignored
+    pub fn parse(spec: &str) -> Self {
+        Self::parse_impl(spec, false).unwrap_or_default()
+    }
+
+    // in the strict mode, the first invalid directive is an error
+    fn parse_impl(
+        spec: &str,
+        strict: bool,
+    ) -> core::result::Result<Self, String> {
+        let mut f = LogFilter::default();
+        for d in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let ok = match d.split_once('=') {
+                Some((m, l)) => l.parse().map(|l| {
+                    f.modules.retain(|(i, _)| i != m.trim());
+                    f.modules.push((m.trim().to_owned(), l));
+                }),
+                None => d.parse().map(|l| f.default = l),
+            };
+            if strict && ok.is_err() {
+                return Err(format!("invalid directive {d:?}"));
+            }
+        }
+        f.modules.sort_by_key(|(m, _)| core::cmp::Reverse(m.len()));
+        Ok(f)
+    }
+
+    /// The effective level of `module`
+    pub fn level_of(&self, module: &str) -> LogLevel {
+        self.modules
+            .iter()
+            .find(|(m, _)| {
+                module.strip_prefix(m.as_str()).is_some_and(|rest| {
+                    rest.is_empty() || rest.starts_with("::")
+                })
+            })
+            .map(|(_, l)| *l)
+            .unwrap_or(self.default)
+    }
+
+    /// Check if `level` logs from `module` should be printed
+    #[inline(always)]
+    pub fn enabled(&self, level: LogLevel, module: &str) -> bool {
+        level >= self.level_of(module)
+    }
+}
+
+impl FromStr for LogFilter {
+    type Err = String;
+
+    /// Unlike [`LogFilter::parse`], any invalid directive is an error
+    fn from_str(spec: &str) -> core::result::Result<Self, String> {
+        Self::parse_impl(spec, true)
+    }
+}
+
+/// The default level, e.g. `"INFO"`,
+/// read from the filter at each use
+#[cfg(feature = "std")]
+#[deprecated(note = "use `log_level`/`set_log_level`/`set_log_filter`")]
+pub static LOG_LEVEL: LegacyLogLevel = LegacyLogLevel(());
+
+/// The type of the deprecated `LOG_LEVEL`,
+/// derefs to the name of the current default level
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct LegacyLogLevel(());
+
+#[cfg(feature = "std")]
+impl core::ops::Deref for LegacyLogLevel {
+    type Target = String;
+
+    fn deref(&self) -> &String {
+        static NAMES: LazyLock<[String; 5]> = LazyLock::new(|| {
+            [
+                LogLevel::Trace,
+                LogLevel::Debug,
+                LogLevel::Info,
+                LogLevel::Warn,
+                LogLevel::Error,
+            ]
+            .map(|l| l.as_str().to_owned())
+        });
+        &NAMES[log_level() as usize]
+    }
+}
+
+#[cfg(feature = "std")]
+/// Check if `level` logs from `module` should be printed
+#[inline(always)]
+pub fn log_enabled(level: LogLevel, module: &str) -> bool {
+    FILTER
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .enabled(level, module)
+}
+
+#[cfg(feature = "std")]
+/// Get the default level
+pub fn log_level() -> LogLevel {
+    FILTER.read().unwrap_or_else(|e| e.into_inner()).default
+}
+
+#[cfg(feature = "std")]
+/// Change the default level, per-module levels are kept
+pub fn set_log_level(level: LogLevel) {
+    FILTER.write().unwrap_or_else(|e| e.into_inner()).default = level;
+}
+
+#[cfg(feature = "std")]
+/// Replace the whole filter, e.g. `set_log_filter("warn,my_app=debug")`
+pub fn set_log_filter(spec: &str) {
+    *FILTER.write().unwrap_or_else(|e| e.into_inner()) =
+        LogFilter::parse(spec);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn t_level_parse() {
+        assert_eq!("debug".parse(), Ok(LogLevel::Debug));
+        assert_eq!(" Warning ".parse(), Ok(LogLevel::Warn));
+        assert!("verbose".parse::<LogLevel>().is_err());
+        assert!(LogLevel::Trace < LogLevel::Error);
+
+        #[cfg(feature = "std")]
+        #[allow(deprecated)]
+        {
+            assert_eq!(LOG_LEVEL.as_str(), log_level().as_str());
+        }
+    }
+
+    #[test]
+    fn t_filter() {
+        let f = LogFilter::parse("warn, app=debug ,app::net=error,x=bad,?");
+        assert_eq!(f.level_of("other"), LogLevel::Warn);
+        assert_eq!(f.level_of("app"), LogLevel::Debug);
+        assert_eq!(f.level_of("app::db"), LogLevel::Debug);
+        assert_eq!(f.level_of("app::net"), LogLevel::Error);
+        assert_eq!(f.level_of("app::net::tcp"), LogLevel::Error);
+        // not a module boundary
+        assert_eq!(f.level_of("application"), LogLevel::Warn);
+        assert_eq!(f.level_of("x"), LogLevel::Warn);
+
+        assert!(f.enabled(LogLevel::Warn, "other"));
+        assert!(!f.enabled(LogLevel::Info, "other"));
+        assert!(f.enabled(LogLevel::Debug, "app::db"));
+        assert!(!f.enabled(LogLevel::Warn, "app::net"));
+
+        assert_eq!(LogFilter::parse(""), LogFilter::default());
+
+        assert_eq!(
+            "warn,app=debug".parse(),
+            Ok(LogFilter::parse("app=debug,warn"))
+        );
+        assert_eq!(
+            "warn,x=bad".parse::<LogFilter>(),
+            Err("invalid directive \"x=bad\"".to_owned())
+        );
+        assert!("verbose".parse::<LogFilter>().is_err());
+    }
+}
@@ -3,18 +3,64 @@
 //!
 //! Useful macros for chained error managements.
 //!
+//! `trace!`, `debug!`, `info!` and `warn!` share their names with the
+//! macros of `log` and `tracing`: with `use ruc::*` and `use log::*`
+//! both in scope, the names are ambiguous. Import one side explicitly
+//! (e.g. `use log::{debug, warn}`, which shadows the glob),
+//! or call them by path, `ruc::warn!(...)`.
+//!
 
-/// Print information only
+#[doc(hidden)]
 #[macro_export]
-macro_rules! info {
-    ($ops: expr, $fmt: expr, $($arg:tt)*) => {{
+macro_rules! __log_chain {
+    ($level: ident, $ops: expr, $fmt: expr, $($arg:tt)*) => {{
         $crate::err::RucResult::c($ops, $crate::d!($fmt, $($arg)*)).map_err(|e| {
-            if "INFO" == $crate::LOG_LEVEL.as_str() {
-                e.print(Some("INFO"));
-            }
+            $crate::err::__log(e.as_ref(), $crate::err::LogLevel::$level, module_path!());
             e
         })
     }};
+}
+
+/// Print the error chain at `TRACE` level, and return it
+///
+/// Ambiguous with `log::trace!`/`tracing::trace!` when both crates are
+/// glob-imported, call it as `ruc::trace!` or import one side explicitly.
+#[macro_export]
+macro_rules! trace {
+    ($ops: expr, $fmt: expr, $($arg:tt)*) => {{
+        $crate::__log_chain!(Trace, $ops, $fmt, $($arg)*)
+    }};
+    ($ops: expr, $msg: expr) => {{
+        $crate::trace!($ops, "{}", $msg)
+    }};
+    ($ops: expr) => {{
+        $crate::trace!($ops, "")
+    }};
+}
+
+/// Print the error chain at `DEBUG` level, and return it
+///
+/// Ambiguous with `log::debug!`/`tracing::debug!` when both crates are
+/// glob-imported, call it as `ruc::debug!` or import one side explicitly.
+#[macro_export]
+macro_rules! debug {
+    ($ops: expr, $fmt: expr, $($arg:tt)*) => {{
+        $crate::__log_chain!(Debug, $ops, $fmt, $($arg)*)
+    }};
+    ($ops: expr, $msg: expr) => {{
+        $crate::debug!($ops, "{}", $msg)
+    }};
+    ($ops: expr) => {{
+        $crate::debug!($ops, "")
+    }};
+}
+
+/// Print information only
+#[macro_export]
+macro_rules! info {
+    ($ops: expr, $fmt: expr, $($arg:tt)*) => {{
+        $crate::__log_chain!(Info, $ops, $fmt, $($arg)*)
+    }};
     ($ops: expr, $msg: expr) => {{
         $crate::info!($ops, "{}", $msg)
     }};
@@ -23,6 +69,23 @@ macro_rules! info {
     }};
 }
 
+/// Print the error chain at `WARN` level, and return it
+///
+/// Ambiguous with `log::warn!`/`tracing::warn!` when both crates are
+/// glob-imported, call it as `ruc::warn!` or import one side explicitly.
+#[macro_export]
+macro_rules! warn {
+    ($ops: expr, $fmt: expr, $($arg:tt)*) => {{
+        $crate::__log_chain!(Warn, $ops, $fmt, $($arg)*)
+    }};
+    ($ops: expr, $msg: expr) => {{
+        $crate::warn!($ops, "{}", $msg)
+    }};
+    ($ops: expr) => {{
+        $crate::warn!($ops, "")
+    }};
+}
+
 /// omit the result without printing any message
 #[macro_export]
 macro_rules! omit {
@@ -46,16 +109,33 @@ macro_rules! info_omit {
 }
 
 /// Generate debug info, e.g., file path, line number, column number
+///
+/// An error code can be attached by a leading `code = N`,
+/// e.g. `d!(code = 404, "not found: {}", id)`;
+/// context fields can be attached after a `;`,
+/// e.g. `d!("open failed"; path = p, uid = u)`.
 #[macro_export]
 macro_rules! d {
+    (code = $code: expr, $($arg:tt)*) => {{
+        $crate::d!($($arg)*).with_code($code)
+    }};
+    (code = $code: expr) => {{
+        $crate::d!().with_code($code)
+    }};
+    ($fmt: expr $(, $arg: expr)* ; $($k: ident = $v: expr),+ $(,)?) => {{
+        $crate::d!($fmt $(, $arg)*)$(.with_field(stringify!($k), $v))+
+    }};
+    (; $($k: ident = $v: expr),+ $(,)?) => {{
+        $crate::d!()$(.with_field(stringify!($k), $v))+
+    }};
     ($fmt: expr, $($arg:tt)*) => {{
-        $crate::err::SimpleMsg::new(format!($fmt, $($arg)*), file!(), line!(), column!())
+        $crate::err::SimpleMsg::new($crate::__private::format!($fmt, $($arg)*), file!(), line!(), column!())
     }};
     ($err: expr) => {{
         $crate::d!("{}", $err)
     }};
     () => {{
-        $crate::err::SimpleMsg::new(String::new(), file!(), line!(), column!())
+        $crate::err::SimpleMsg::new($crate::__private::String::new(), file!(), line!(), column!())
     }};
 }
 
@@ -81,12 +161,12 @@ macro_rules! eprint_msg {
     }};
 }
 
-/// Just a panic
+/// Print the message through the global sink, and panic;
+/// without `std`, the message is the panic message instead
 #[macro_export]
 macro_rules! die {
     ($fmt: expr, $($arg:tt)*) => {{
-        $crate::print_msg!($fmt, $($arg)*);
-        panic!();
+        $crate::err::__die(&$crate::__private::format!("\n{}", $crate::d!($fmt, $($arg)*)))
     }};
     ($msg: expr) => {{
         $crate::die!("{}", $msg)
@@ -111,18 +191,12 @@ macro_rules! pnk {
     }};
 }
 
-/// Generate error with debug info
+/// Generate error with debug info, accepts the same arguments as `d!`
 #[macro_export]
 macro_rules! eg {
-    ($fmt: expr, $($arg:tt)*) => {{
-        Box::new($crate::err::SimpleError::new($crate::d!($fmt, $($arg)*), None))
-            as Box<dyn $crate::err::RucError>
-    }};
-    ($err: expr) => {{
-        $crate::eg!("{}", $err)
-    }};
-    () => {{
-        $crate::eg!("")
+    ($($arg:tt)*) => {{
+        $crate::__private::Box::new($crate::err::SimpleError::new($crate::d!($($arg)*), None))
+            as $crate::__private::Box<dyn $crate::err::RucError>
     }};
 }
 
@@ -159,7 +233,10 @@ mod tests {
             assert_eq!(2 * k, v);
         }
 
+        let _ = trace!(Err::<u8, _>(eg!()));
+        let _ = debug!(Err::<u8, _>(eg!()), "x = {}", 1);
         let _ = info!(Err::<u8, _>(eg!()));
+        let _ = warn!(Err::<u8, _>(eg!()), "warn");
         omit!(Err::<u8, _>(eg!()));
         info_omit!(Err::<u8, _>(eg!()));
         print_msg!("{:?}", ts!());
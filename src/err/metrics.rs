@@ -0,0 +1,163 @@
+//!
+//! # Error metrics
+//!
+//! An optional global registry counting every root cause
+//! created(by `eg!`, `.c(d!())` on a foreign error, etc.),
+//! grouped by its call site and its type, so a daemon can expose
+//! error rates per location without parsing logs;
+//! the levels added on top of an existing chain are not counted.
+//!
+//! Disabled by default, see [`set_error_metrics`].
+//!
+
+use core::{any::TypeId, fmt::Write};
+use std::{
+    collections::HashMap,
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+type Key = (&'static str, u32, TypeId);
+
+static COUNTERS: LazyLock<Mutex<HashMap<Key, (String, u64)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The count of errors created at one call site,
+/// with the same type of the lowest error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorCount {
+    /// file path of the call site
+    pub file: &'static str,
+    /// line number of the call site
+    pub line: u32,
+    /// type of the lowest(root cause) error
+    pub type_id: TypeId,
+    /// type name of the lowest(root cause) error
+    pub type_name: String,
+    /// number of errors
+    pub count: u64,
+}
+
+/// Enable or disable the counting, default to `false`
+pub fn set_error_metrics(on: bool) {
+    ENABLED.store(on, Ordering::Relaxed);
+}
+
+/// Check if the counting is enabled
+pub fn error_metrics_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub(super) fn record_error(
+    file: &'static str,
+    line: u32,
+    type_id: TypeId,
+    type_name: &str,
+) {
+    let mut counters = COUNTERS.lock().unwrap_or_else(|e| e.into_inner());
+    counters
+        .entry((file, line, type_id))
+        .or_insert_with(|| (type_name.to_owned(), 0))
+        .1 += 1;
+}
+
+/// A snapshot of all the counters, sorted by call site
+pub fn error_metrics() -> Vec<ErrorCount> {
+    let counters = COUNTERS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut res = counters
+        .iter()
+        .map(|((file, line, type_id), (type_name, count))| ErrorCount {
+            file,
+            line: *line,
+            type_id: *type_id,
+            type_name: type_name.clone(),
+            count: *count,
+        })
+        .collect::<Vec<_>>();
+    drop(counters);
+    res.sort_by(|a, b| {
+        (a.file, a.line, &a.type_name).cmp(&(b.file, b.line, &b.type_name))
+    });
+    res
+}
+
+/// Clear all the counters
+pub fn reset_error_metrics() {
+    COUNTERS.lock().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+/// All the counters in the Prometheus text format,
+/// as a counter named `ruc_errors_total`
+pub fn error_metrics_prometheus() -> String {
+    let mut res = String::from(
+        "# HELP ruc_errors_total Errors created, by call site and root cause type.\n# TYPE ruc_errors_total counter\n",
+    );
+    for c in error_metrics() {
+        // writing to a `String` never fails
+        let _ = writeln!(
+            res,
+            "ruc_errors_total{{file=\"{}\",line=\"{}\",type=\"{}\"}} {}",
+            escape_label(c.file),
+            c.line,
+            escape_label(&c.type_name),
+            c.count
+        );
+    }
+    res
+}
+
+fn escape_label(v: &str) -> String {
+    v.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::*;
+
+    fn mine() -> Vec<ErrorCount> {
+        error_metrics()
+            .into_iter()
+            .filter(|c| c.file == file!())
+            .collect()
+    }
+
+    #[test]
+    fn t_error_metrics() {
+        // other tests may create errors concurrently,
+        // so only the ones of this file are checked
+        set_error_metrics(true);
+        for i in 0..3 {
+            // only the root cause is counted, not the outer level
+            let _ = "x".parse::<u8>().c(d!("parse {}", i)).c(d!("outer"));
+        }
+        let _ = eg!("plain");
+        set_error_metrics(false);
+        let _ = eg!("not counted");
+
+        let m = mine();
+        assert_eq!(m.len(), 2);
+        assert_eq!(m[0].count, 3);
+        assert_eq!(m[0].type_id, TypeId::of::<core::num::ParseIntError>());
+        assert!(m[0].type_name.ends_with("ParseIntError"));
+        assert_eq!(m[1].count, 1);
+        assert_eq!(m[1].type_id, TypeId::of::<String>());
+        assert!(m[0].line < m[1].line);
+
+        let text = error_metrics_prometheus();
+        assert!(text.contains("# TYPE ruc_errors_total counter\n"));
+        assert!(text.contains(&format!(
+            "ruc_errors_total{{file=\"{}\",line=\"{}\",type=\"core::num::error::ParseIntError\"}} 3\n",
+            file!(),
+            m[0].line
+        )));
+
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -25,40 +25,124 @@
 
 mod macros;
 
+mod level;
+mod multi;
+mod style;
+
+#[cfg(feature = "std")]
+mod metrics;
+#[cfg(feature = "std")]
+mod panic;
+#[cfg(feature = "std")]
+mod shared;
+#[cfg(feature = "std")]
+mod sink;
+#[cfg(feature = "std")]
+mod span;
+#[cfg(feature = "std")]
+mod throttle;
+
+#[cfg(feature = "std")]
+pub mod testing;
+
+#[cfg(feature = "serde")]
+mod remote;
+#[cfg(feature = "serde")]
+mod report;
+
+pub use level::*;
+pub use multi::*;
+pub use style::*;
+
+#[cfg(feature = "std")]
+pub use metrics::*;
+#[cfg(feature = "std")]
+pub use panic::*;
+#[cfg(feature = "std")]
+pub use shared::*;
+#[cfg(feature = "std")]
+pub use sink::*;
+#[cfg(feature = "std")]
+pub use span::*;
+#[cfg(feature = "std")]
+pub use throttle::*;
+
+#[cfg(feature = "serde")]
+pub use remote::*;
+#[cfg(feature = "serde")]
+pub use report::*;
+
+use alloc::{
+    borrow::ToOwned,
+    boxed::Box,
+    collections::BTreeSet,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
 use core::{
     any::{Any, TypeId},
     fmt::{Debug, Display},
 };
 
-use std::{collections::HashSet, sync::LazyLock};
+#[cfg(feature = "std")]
+use std::{
+    backtrace::{Backtrace, BacktraceStatus},
+    sync::LazyLock,
+};
 
-/// `INFO` or `ERROR`, if mismatch, default to `INFO`
-pub static LOG_LEVEL: LazyLock<String> = LazyLock::new(|| {
-    if let Ok(l) = std::env::var("RUC_LOG_LEVEL")
-        && "ERROR" == l
-    {
-        return "ERROR".to_owned();
-    }
-    "INFO".to_owned()
+// `RUC_BACKTRACE` set to anything but `0`, or the `backtrace` feature
+#[cfg(feature = "std")]
+static FORCE_BACKTRACE: LazyLock<bool> = LazyLock::new(|| {
+    cfg!(feature = "backtrace")
+        || std::env::var("RUC_BACKTRACE").is_ok_and(|v| "0" != v)
 });
 
+// see `SimpleError::new`
+#[cfg(feature = "std")]
+fn capture_backtrace() -> Option<Backtrace> {
+    if *FORCE_BACKTRACE {
+        Some(Backtrace::force_capture())
+    } else {
+        Some(Backtrace::capture())
+            .filter(|bt| BacktraceStatus::Captured == bt.status())
+    }
+}
+
+#[cfg(feature = "std")]
 static PID: LazyLock<u32> = LazyLock::new(std::process::id);
+#[cfg(feature = "std")]
 static PID_NS: LazyLock<String> =
     LazyLock::new(|| get_pidns(*PID).unwrap_or_else(|_| "UNKNOWN".to_owned()));
 
 /// Custom Result
 pub type Result<T> = core::result::Result<T, Box<dyn RucError>>;
 
+/// Upcast any [`RucError`] implementation to `&dyn RucError`,
+/// implemented automatically for every sized implementor.
+pub trait AsRucError {
+    /// get a trait object of `self`
+    fn as_ruc_error(&self) -> &dyn RucError;
+}
+
+impl<T: RucError> AsRucError for T {
+    #[inline(always)]
+    fn as_ruc_error(&self) -> &dyn RucError {
+        self
+    }
+}
+
 /// The major trait definition
-pub trait RucError: Display + Debug + Send {
+pub trait RucError: Display + Debug + Send + AsRucError {
     /// type id of current error type
     fn type_id(&self) -> TypeId;
 
-    /// type ids of errors of each level(from top to bottom).
+    /// type ids of errors of each level(from top to bottom),
+    /// along the `cause` chain, branches excluded
     fn type_ids(&self) -> Vec<TypeId> {
         let mut res = Vec::new();
-        res.push(self.type_id());
-        let mut current = self.cause();
+        let mut current = Some(self.as_ruc_error());
         while let Some(c) = current {
             res.push(c.type_id());
             current = c.cause();
@@ -76,9 +160,9 @@ pub trait RucError: Display + Debug + Send {
         self.lowest_type_id() == e.type_id()
     }
 
-    /// check if an error exists in the error chain
+    /// check if an error exists in the error chain(branches included)
     fn contains_type(&self, e: &dyn Any) -> bool {
-        self.type_ids().contains(&e.type_id())
+        has_type_id(self.as_ruc_error(), e.type_id())
     }
 
     /// Compare two objects
@@ -86,27 +170,14 @@ pub trait RucError: Display + Debug + Send {
         self.get_lowest_msg() == another.get_lowest_msg()
     }
 
-    /// check if any node from the error_chain matches the given error
+    /// check if any node from the error_chain(branches included)
+    /// matches the given error
     fn msg_has_overlap(&self, another: &dyn RucError) -> bool {
-        let mut self_list = HashSet::new();
-        self_list.insert(self.get_top_msg());
-        let mut b = self.cause();
-        while let Some(next) = b {
-            self_list.insert(next.get_top_msg());
-            b = next.cause();
-        }
-
-        if self_list.contains(&another.get_top_msg()) {
-            return true;
-        }
-        b = another.cause();
-        while let Some(next) = b {
-            if self_list.contains(&next.get_top_msg()) {
-                return true;
-            }
-            b = next.cause();
-        }
-        false
+        let mut self_list = BTreeSet::new();
+        collect_top_msgs(self.as_ruc_error(), &mut self_list);
+        let mut another_list = BTreeSet::new();
+        collect_top_msgs(another, &mut another_list);
+        !self_list.is_disjoint(&another_list)
     }
 
     /// convert the error of current level to string
@@ -127,32 +198,150 @@ pub trait RucError: Display + Debug + Send {
         None
     }
 
-    /// generate the final error msg
-    fn stringify_chain(&self, prefix: Option<&str>) -> String {
-        let mut res =
-            format!("{}{}: ", delimiter(), prefix.unwrap_or("ERROR"));
-        res.push_str(&self.get_top_msg_with_dbginfo());
+    /// Independent errors aggregated by current level,
+    /// see [`MultiError`]
+    fn branches(&self) -> &[Box<dyn RucError>] {
+        &[]
+    }
+
+    /// error code of current level, if attached
+    fn top_code(&self) -> Option<i32> {
+        None
+    }
+
+    /// the first(from top to bottom) error code in the chain
+    fn code(&self) -> Option<i32> {
+        self.top_code().or_else(|| {
+            let mut e = self.cause();
+            while let Some(c) = e {
+                if let Some(code) = c.top_code() {
+                    return Some(code);
+                }
+                e = c.cause();
+            }
+            None
+        })
+    }
+
+    /// all error codes in the chain, from top to bottom
+    fn codes(&self) -> Vec<i32> {
+        let mut res = Vec::from_iter(self.top_code());
         let mut e = self.cause();
-        let mut indent_num = 0;
         while let Some(c) = e {
-            let mut prefix = delimiter().to_owned();
-            (0..indent_num).for_each(|_| {
-                prefix.push_str(indent());
-            });
-            res.push_str(&prefix);
-            res.push_str("Caused By: ");
-            res.push_str(&c.get_top_msg_with_dbginfo().replace('\n', &prefix));
-            indent_num += 1;
+            res.extend(c.top_code());
             e = c.cause();
         }
         res
     }
 
-    /// Panic after printing `error_chain`
+    /// context fields of current level
+    fn top_fields(&self) -> Vec<(&str, &str)> {
+        vec![]
+    }
+
+    /// context fields of all levels, from top to bottom
+    fn fields(&self) -> Fields<'_> {
+        Fields {
+            cur: self.top_fields().into_iter(),
+            next: self.cause(),
+        }
+    }
+
+    /// the span stack when current level was created
+    fn top_spans(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    /// the thread which created current level, if recorded
+    fn top_thread(&self) -> Option<&str> {
+        None
+    }
+
+    /// messages of the `Error::source` chain of the foreign error
+    /// of current level, from top to bottom, see [`RucResult::c`]
+    fn top_sources(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    /// The cause of current level as a `core::error::Error`,
+    /// so the chain is visible to anything walking `Error::source`
+    fn std_source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        None
+    }
+
+    /// type name of the error of current level
+    fn type_name(&self) -> &str {
+        ""
+    }
+
+    /// `(file, line, column)` of current level, if recorded
+    fn location(&self) -> Option<(&str, u32, u32)> {
+        None
+    }
+
+    /// The original error value of current level,
+    /// used by [`downcast_ref`](trait.RucError.html#method.downcast_ref)
+    /// and [`find`](trait.RucError.html#method.find)
+    fn origin(&self) -> Option<&(dyn Any + Send)> {
+        None
+    }
+
+    /// Check if current level was received from another process,
+    /// e.g. a `RemoteError` decoded from a `WireError`
+    fn is_remote(&self) -> bool {
+        false
+    }
+
+    /// The stack captured when the lowest error was created,
+    /// see [`SimpleError::new`]
+    #[cfg(feature = "std")]
+    fn backtrace(&self) -> Option<&Backtrace> {
+        None
+    }
+
+    /// generate the final error msg, see [`set_render_policy`]
+    fn stringify_chain(&self, prefix: Option<&str>) -> String {
+        self.stringify_chain_with(prefix, render_policy())
+    }
+
+    /// generate the final error msg with the given policy
+    fn stringify_chain_with(
+        &self,
+        prefix: Option<&str>,
+        policy: RenderPolicy,
+    ) -> String {
+        let style = log_style();
+        let mut res = String::new();
+        render_chain(
+            self.as_ruc_error(),
+            prefix.unwrap_or("ERROR"),
+            0,
+            style,
+            policy,
+            &mut res,
+        );
+        #[cfg(feature = "std")]
+        if let Some(bt) = self.get_lowest_err().backtrace() {
+            res.push_str(style.delimiter());
+            res.push_str("Backtrace:");
+            res.push_str(style.delimiter());
+            res.push_str(&bt.to_string().replace('\n', style.delimiter()));
+        }
+        res
+    }
+
+    /// Panic after printing `error_chain`,
+    /// without `std`, the chain is the panic message instead
     #[inline(always)]
     fn print_die(&self) -> ! {
-        self.print(None);
-        panic!();
+        #[cfg(feature = "std")]
+        {
+            self.print(None);
+            __mark_reported();
+            panic!();
+        }
+        #[cfg(not(feature = "std"))]
+        panic!("{}", self.generate_log(None));
     }
 
     /// Generate the log string
@@ -163,38 +352,269 @@ pub trait RucError: Display + Debug + Send {
 
     /// Generate the log string with custom mode
     fn generate_log_custom(&self, prefix: Option<&str>) -> String {
-        #[cfg(feature = "ansi")]
-        #[inline(always)]
-        fn generate_log_header(ns: &str, pid: u32) -> String {
-            format!(
-                "\n\x1b[31;01m# {time} [pid: {pid}] [pidns: {ns}]\x1b[00m",
-                time = crate::datetime!(),
-                pid = pid,
-                ns = ns,
-            )
-        }
-
-        #[cfg(not(feature = "ansi"))]
-        #[inline(always)]
-        fn generate_log_header(ns: &str, pid: u32) -> String {
-            format!(
-                "\n# {time} [pid: {pid}] [pidns: {ns}]",
-                time = crate::datetime!(),
-                pid = pid,
-                ns = ns,
-            )
-        }
-
+        #[cfg(feature = "std")]
         let mut res = generate_log_header(&PID_NS, *PID);
+        // no clock or pid without `std`
+        #[cfg(not(feature = "std"))]
+        let mut res = String::new();
         res.push_str(&self.stringify_chain(prefix));
         res
     }
 
-    /// Print log
+    /// Collect the whole chain, along with the log header,
+    /// into a serializable record
+    #[cfg(feature = "serde")]
+    fn serialize_chain(&self, prefix: Option<&str>) -> LogRecord {
+        let chain = LevelRecord::chain(self.as_ruc_error(), true);
+        LogRecord::new(prefix, chain)
+    }
+
+    /// Convert the whole chain(branches included) into a wire form,
+    /// which can be sent to another process and turned back into
+    /// a `Box<dyn RucError>` there, see [`WireError`]
+    #[cfg(feature = "serde")]
+    fn to_wire(&self) -> WireError {
+        WireError::new(self.as_ruc_error())
+    }
+
+    /// Generate the log as a single-line JSON object
+    #[cfg(feature = "ende_json")]
+    fn to_json(&self, prefix: Option<&str>) -> String {
+        // plain strings and integers, serialization can not fail
+        serde_json::to_string(&self.serialize_chain(prefix))
+            .unwrap_or_default()
+    }
+
+    /// Print log, through the global [`LogSink`],
+    /// at the level named by `prefix`(e.g. `Some("WARN")`),
+    /// or `ERROR` if it is not a level name
+    #[cfg(feature = "std")]
     #[inline(always)]
     fn print(&self, prefix: Option<&str>) {
+        let level = prefix
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(LogLevel::Error);
         let msg = self.generate_log(prefix);
-        eprintln!("{}", msg);
+        emit(level, &msg, Some(self.as_ruc_error()));
+    }
+
+    /// Print log with the name of `level` as the prefix,
+    /// through the global [`LogSink`]
+    #[cfg(feature = "std")]
+    #[inline(always)]
+    fn print_level(&self, level: LogLevel) {
+        let msg = self.generate_log(Some(level.as_str()));
+        emit(level, &msg, Some(self.as_ruc_error()));
+    }
+}
+
+// `info!`, `warn!`, etc. print through this,
+// nothing is printed without `std`;
+// the caller is the call site for throttling
+#[doc(hidden)]
+#[track_caller]
+pub fn __log(e: &dyn RucError, level: LogLevel, module: &str) {
+    #[cfg(feature = "std")]
+    if log_enabled(level, module) {
+        let loc = core::panic::Location::caller();
+        throttle::print_throttled(e, level, loc);
+    }
+    #[cfg(not(feature = "std"))]
+    let _ = (e, level, module);
+}
+
+// `die!` panics through this
+#[doc(hidden)]
+pub fn __die(msg: &str) -> ! {
+    #[cfg(feature = "std")]
+    {
+        emit(LogLevel::Error, msg, None);
+        __mark_reported();
+        panic!();
+    }
+    #[cfg(not(feature = "std"))]
+    panic!("{}", msg);
+}
+
+// Render `e` and its causes, the top level is labeled by `label`,
+// causes get one more indent per level, starting from the second one;
+// the branches of a level are nested one indent deeper than the level.
+fn render_chain(
+    e: &dyn RucError,
+    label: &str,
+    indent_num: usize,
+    style: LogStyle,
+    policy: RenderPolicy,
+    res: &mut String,
+) {
+    // (level, how many times it repeats consecutively)
+    let mut levels: Vec<(&dyn RucError, usize)> = vec![];
+    let mut current = Some(e);
+    while let Some(c) = current {
+        match levels.last_mut() {
+            Some((last, n))
+                if policy.collapse_repeats && same_level(*last, c) =>
+            {
+                *n += 1;
+            }
+            _ => levels.push((c, 1)),
+        }
+        current = c.cause();
+    }
+
+    // elide the middle levels, the root cause is always kept
+    let mut elided = 0;
+    let max_depth = policy.max_depth.map(|n| n.max(1)).unwrap_or(usize::MAX);
+    if max_depth < levels.len() {
+        let root = levels.len() - 1;
+        elided = levels.drain(max_depth - 1..root).map(|(_, n)| n).sum();
+    }
+
+    let mut label = label;
+    let mut indent_num = indent_num;
+    // levels and sources rendered so far
+    let mut depth = 0;
+    for (i, (c, n)) in levels.into_iter().enumerate() {
+        if 1 < depth {
+            indent_num += 1;
+        }
+        let prefix = line_prefix(style, indent_num);
+        if 0 < elided && i == max_depth - 1 {
+            res.push_str(&prefix);
+            res.push_str(&format!("... {elided} levels elided ..."));
+        }
+        res.push_str(&prefix);
+        res.push_str(label);
+        res.push_str(": ");
+        let mut msg = c.get_top_msg_with_dbginfo();
+        if let Some(max) = policy.max_msg_len {
+            let top = c.get_top_msg();
+            if let Some(t) = truncate_msg(&top, max) {
+                msg = msg.replacen(&top, &t, 1);
+            }
+        }
+        res.push_str(&msg.replace('\n', &prefix));
+        if 1 < n {
+            res.push_str(&prefix);
+            res.push_str(&format!("(repeated {n} times)"));
+        }
+        let branches = c.branches();
+        for (i, b) in branches.iter().enumerate() {
+            let label = format!("Branch [{}/{}]", i + 1, branches.len());
+            render_chain(
+                b.as_ref(),
+                &label,
+                indent_num + 1,
+                style,
+                policy,
+                res,
+            );
+        }
+        label = "Caused By";
+        depth += 1;
+
+        // one more cause each, without debug info
+        for src in c.top_sources() {
+            if 1 < depth {
+                indent_num += 1;
+            }
+            let prefix = line_prefix(style, indent_num);
+            let src = policy
+                .max_msg_len
+                .and_then(|max| truncate_msg(src, max))
+                .unwrap_or_else(|| src.to_owned());
+            res.push_str(&prefix);
+            res.push_str(label);
+            res.push_str(": ");
+            res.push_str(&src.replace('\n', &prefix));
+            depth += 1;
+        }
+    }
+}
+
+// a line break followed by `indent_num` indents
+fn line_prefix(style: LogStyle, indent_num: usize) -> String {
+    let mut prefix = style.delimiter().to_owned();
+    (0..indent_num).for_each(|_| {
+        prefix.push_str(style.indent());
+    });
+    prefix
+}
+
+// same message at the same location
+fn same_level(a: &dyn RucError, b: &dyn RucError) -> bool {
+    a.location() == b.location() && a.get_top_msg() == b.get_top_msg()
+}
+
+// `None` if `msg` is not longer than `max` chars
+fn truncate_msg(msg: &str, max: usize) -> Option<String> {
+    let (idx, _) = msg.char_indices().nth(max)?;
+    let rest = msg[idx..].chars().count();
+    Some(format!("{}...({rest} chars truncated)", &msg[..idx]))
+}
+
+fn has_type_id(e: &dyn RucError, id: TypeId) -> bool {
+    core::iter::successors(Some(e), |c| c.cause()).any(|c| {
+        id == c.type_id()
+            || c.branches().iter().any(|b| has_type_id(b.as_ref(), id))
+    })
+}
+
+fn collect_top_msgs(e: &dyn RucError, set: &mut BTreeSet<String>) {
+    let mut current = Some(e);
+    while let Some(c) = current {
+        set.insert(c.get_top_msg());
+        set.extend(c.top_sources().into_iter().map(ToOwned::to_owned));
+        c.branches()
+            .iter()
+            .for_each(|b| collect_top_msgs(b.as_ref(), set));
+        current = c.cause();
+    }
+}
+
+/// Iterator over the context fields of an error chain,
+/// see [`RucError::fields`]
+pub struct Fields<'a> {
+    cur: vec::IntoIter<(&'a str, &'a str)>,
+    next: Option<&'a dyn RucError>,
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(kv) = self.cur.next() {
+                return Some(kv);
+            }
+            let e = self.next?;
+            self.cur = e.top_fields().into_iter();
+            self.next = e.cause();
+        }
+    }
+}
+
+impl dyn RucError + '_ {
+    /// Downcast the lowest(root cause) error to its original type,
+    /// e.g. the `io::Error` wrapped by several `.c(d!())` layers.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.get_lowest_err().origin()?.downcast_ref::<T>()
+    }
+
+    /// Find the first(from top to bottom) error of type `T` in the chain,
+    /// the branches of a level are searched right after it
+    pub fn find<T: Any>(&self) -> Option<&T> {
+        let mut e = Some(self);
+        while let Some(c) = e {
+            if let Some(v) = c.origin().and_then(|o| o.downcast_ref::<T>()) {
+                return Some(v);
+            }
+            if let Some(v) = c.branches().iter().find_map(|b| b.find::<T>()) {
+                return Some(v);
+            }
+            e = c.cause();
+        }
+        None
     }
 }
 
@@ -231,18 +651,9 @@ impl<T, E: Debug + Display + Send + 'static, ERR: Display + Send + 'static>
     #[inline(always)]
     fn c(self, msg: SimpleMsg<E>) -> Result<T> {
         self.map_err(|e| {
-            let err_str = e.to_string();
-            let any_e: Box<dyn Any + Send> = Box::new(e);
-            let cause: Box<dyn RucError> =
-                match any_e.downcast::<Box<dyn RucError>>() {
-                    Ok(ruc_err) => *ruc_err,
-                    Err(_) => {
-                        let inner = SimpleMsg::new(
-                            err_str, msg.file, msg.line, msg.column,
-                        );
-                        Box::new(SimpleError::new(inner, None))
-                    }
-                };
+            let cause = chain_foreign(e, msg.location(), |e| {
+                source_msgs(carrier_source(e))
+            });
             SimpleError::new(msg, Some(cause)).into()
         })
     }
@@ -256,18 +667,161 @@ impl<T, E: Debug + Display + Send + 'static, ERR: Display + Send + 'static>
     }
 }
 
+/// Chain a `Result<T, ERR: core::error::Error>` into `ruc::Result<T>`,
+/// keeping every level of its `Error::source` chain.
+///
+/// [`RucResult::c`] only knows that its error is `Display`, so it can
+/// only walk the sources of `io::Error` and `Box<dyn Error + Send(+ Sync)>`.
+pub trait RucStdResult<T, E: Debug + Display + Send + 'static> {
+    /// Like [`RucResult::c`], with the messages of every source
+    /// kept by the lowest level, see [`RucError::top_sources`]
+    fn c_src(self, msg: SimpleMsg<E>) -> Result<T>;
+}
+
+impl<
+    T,
+    E: Debug + Display + Send + 'static,
+    ERR: core::error::Error + Send + 'static,
+> RucStdResult<T, E> for core::result::Result<T, ERR>
+{
+    #[inline(always)]
+    fn c_src(self, msg: SimpleMsg<E>) -> Result<T> {
+        self.map_err(|e| {
+            let sources = source_msgs(e.source());
+            let cause = chain_foreign(e, msg.location(), |_| sources);
+            SimpleError::new(msg, Some(cause)).into()
+        })
+    }
+}
+
+// Convert a foreign error into the lowest level located at `loc`,
+// the error itself is kept as the origin for downcasting,
+// along with the messages of its sources.
+// A `Box<dyn RucError>` is returned as-is, so is a `SharedError`.
+fn chain_foreign<ERR: Display + Send + 'static>(
+    e: ERR,
+    loc: (&'static str, u32, u32),
+    sources: impl FnOnce(&(dyn Any + Send)) -> Vec<String>,
+) -> Box<dyn RucError> {
+    let err_str = e.to_string();
+    let any_e: Box<dyn Any + Send> = Box::new(e);
+    #[cfg(feature = "std")]
+    let any_e = match any_e.downcast::<SharedError>() {
+        Ok(shared) => return (*shared).into(),
+        Err(any_e) => any_e,
+    };
+    match any_e.downcast::<Box<dyn RucError>>() {
+        Ok(ruc_err) => *ruc_err,
+        Err(any_e) => {
+            let (file, line, column) = loc;
+            let sources = SourceMsg::chain(sources(any_e.as_ref()));
+            let inner = SimpleMsg::new(err_str, file, line, column);
+            let origin = (any_e, core::any::type_name::<ERR>());
+            let mut e = SimpleError::build(inner, None, Some(origin));
+            e.sources = sources;
+            #[cfg(feature = "std")]
+            e.record();
+            Box::new(e)
+        }
+    }
+}
+
+// The `Error::source` of the type-erased std errors `.c()` can recognize
+fn carrier_source(
+    e: &(dyn Any + Send),
+) -> Option<&(dyn core::error::Error + 'static)> {
+    #[cfg(feature = "std")]
+    if let Some(e) = e.downcast_ref::<std::io::Error>() {
+        return core::error::Error::source(e);
+    }
+    if let Some(e) =
+        e.downcast_ref::<Box<dyn core::error::Error + Send + Sync>>()
+    {
+        return e.source();
+    }
+    e.downcast_ref::<Box<dyn core::error::Error + Send>>()
+        .and_then(|e| e.source())
+}
+
+fn source_msgs(
+    src: Option<&(dyn core::error::Error + 'static)>,
+) -> Vec<String> {
+    core::iter::successors(src, |e| e.source())
+        .map(|e| e.to_string())
+        .collect()
+}
+
 /// A pre-implemented Error
 #[derive(Debug)]
 pub struct SimpleError<E: Debug + Display + Send + 'static> {
     msg: SimpleMsg<E>,
-    cause: Option<Box<dyn RucError>>,
+    cause: Option<Link>,
+    #[cfg(feature = "std")]
+    backtrace: Option<Backtrace>,
+    // the foreign error converted into `msg` by `RucResult::c`,
+    // along with its type name
+    origin: Option<(Box<dyn Any + Send>, &'static str)>,
+    // the `Error::source` chain of the origin
+    sources: Option<Box<SourceMsg>>,
 }
 
 impl<E: Debug + Display + Send + 'static> SimpleError<E> {
     /// Create a new error with the given message and optional cause.
+    ///
+    /// The lowest error(without a cause) captures a backtrace
+    /// if `RUC_BACKTRACE`(or the `backtrace` feature),
+    /// `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` is enabled.
+    ///
+    /// A root cause(without a cause) is counted by the
+    /// [error metrics](set_error_metrics) if enabled,
+    /// the levels wrapping it are not.
     #[inline(always)]
     pub fn new(msg: SimpleMsg<E>, cause: Option<Box<dyn RucError>>) -> Self {
-        SimpleError { msg, cause }
+        let is_root = cause.is_none();
+        let e = Self::build(msg, cause, None);
+        #[cfg(feature = "std")]
+        if is_root {
+            e.record();
+        }
+        #[cfg(not(feature = "std"))]
+        let _ = is_root;
+        e
+    }
+
+    // count a new root cause, by its location and type
+    #[cfg(feature = "std")]
+    fn record(&self) {
+        if error_metrics_enabled() {
+            let type_id = RucError::type_id(self);
+            record_error(
+                self.msg.file,
+                self.msg.line,
+                type_id,
+                self.type_name(),
+            );
+        }
+    }
+
+    // not counted by the error metrics
+    fn build(
+        msg: SimpleMsg<E>,
+        cause: Option<Box<dyn RucError>>,
+        origin: Option<(Box<dyn Any + Send>, &'static str)>,
+    ) -> Self {
+        #[cfg(feature = "std")]
+        let msg = msg.capture_context();
+        SimpleError {
+            #[cfg(feature = "std")]
+            backtrace: if cause.is_some() {
+                None
+            } else {
+                capture_backtrace()
+            },
+            msg,
+            cause: cause.map(Link),
+            origin,
+            sources: None,
+        }
     }
 }
 
@@ -285,9 +839,70 @@ impl<E: Debug + Display + Send + 'static> From<SimpleError<E>>
     }
 }
 
-impl<E: Debug + Display + Send + 'static> std::error::Error
+impl<E: Debug + Display + Send + 'static> core::error::Error
     for SimpleError<E>
 {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        self.std_source()
+    }
+}
+
+impl core::error::Error for Box<dyn RucError> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        (**self).std_source()
+    }
+}
+
+// A cause in the chain, as seen by `core::error::Error::source`:
+// displays the message of its own level only,
+// instead of the whole sub-chain.
+#[derive(Debug)]
+struct Link(Box<dyn RucError>);
+
+impl Display for Link {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0.get_top_msg())
+    }
+}
+
+impl core::error::Error for Link {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        self.0.std_source()
+    }
+}
+
+// One message of the `Error::source` chain of a foreign error,
+// the chain stays visible to anything walking `Error::source`.
+#[derive(Debug)]
+struct SourceMsg {
+    msg: String,
+    next: Option<Box<SourceMsg>>,
+}
+
+impl SourceMsg {
+    fn chain(msgs: Vec<String>) -> Option<Box<Self>> {
+        msgs.into_iter()
+            .rev()
+            .fold(None, |next, msg| Some(Box::new(SourceMsg { msg, next })))
+    }
+
+    fn msgs(chain: Option<&Self>) -> Vec<&str> {
+        core::iter::successors(chain, |s| s.next.as_deref())
+            .map(|s| s.msg.as_str())
+            .collect()
+    }
+}
+
+impl Display for SourceMsg {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.msg)
+    }
+}
+
+impl core::error::Error for SourceMsg {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        self.next.as_deref().map(|s| s as _)
+    }
 }
 
 impl From<&'static str> for Box<dyn RucError> {
@@ -304,7 +919,52 @@ impl From<String> for Box<dyn RucError> {
 
 impl<E: Debug + Display + Send + 'static> RucError for SimpleError<E> {
     fn type_id(&self) -> TypeId {
-        TypeId::of::<E>()
+        match self.origin.as_ref() {
+            Some((o, _)) => (**o).type_id(),
+            None => TypeId::of::<E>(),
+        }
+    }
+
+    fn type_name(&self) -> &str {
+        match self.origin.as_ref() {
+            Some((_, name)) => name,
+            None => core::any::type_name::<E>(),
+        }
+    }
+
+    fn origin(&self) -> Option<&(dyn Any + Send)> {
+        match self.origin.as_ref() {
+            Some((o, _)) => Some(o.as_ref()),
+            None => Some(&self.msg.err),
+        }
+    }
+
+    fn location(&self) -> Option<(&str, u32, u32)> {
+        Some((self.msg.file, self.msg.line, self.msg.column))
+    }
+
+    fn top_code(&self) -> Option<i32> {
+        self.msg.code
+    }
+
+    fn top_fields(&self) -> Vec<(&str, &str)> {
+        self.msg
+            .fields
+            .iter()
+            .map(|(k, v)| (*k, v.as_str()))
+            .collect()
+    }
+
+    fn top_spans(&self) -> Vec<&str> {
+        self.msg.spans.iter().map(|s| s.as_str()).collect()
+    }
+
+    fn top_thread(&self) -> Option<&str> {
+        self.msg.thread.as_deref()
+    }
+
+    fn top_sources(&self) -> Vec<&str> {
+        SourceMsg::msgs(self.sources.as_deref())
     }
 
     /// get the top-level error message
@@ -316,7 +976,7 @@ impl<E: Debug + Display + Send + 'static> RucError for SimpleError<E> {
     /// get the final(lowest) error message
     #[inline(always)]
     fn get_lowest_msg(&self) -> String {
-        if let Some(next) = self.cause.as_ref() {
+        if let Some(next) = self.cause() {
             next.get_lowest_msg()
         } else {
             self.msg.err.to_string()
@@ -324,7 +984,7 @@ impl<E: Debug + Display + Send + 'static> RucError for SimpleError<E> {
     }
 
     fn get_lowest_err(&self) -> &dyn RucError {
-        if let Some(next) = self.cause.as_ref() {
+        if let Some(next) = self.cause() {
             next.get_lowest_err()
         } else {
             self
@@ -338,12 +998,27 @@ impl<E: Debug + Display + Send + 'static> RucError for SimpleError<E> {
 
     #[inline(always)]
     fn cause(&self) -> Option<&dyn RucError> {
-        self.cause.as_deref()
+        self.cause.as_ref().map(|l| l.0.as_ref())
+    }
+
+    fn std_source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self.cause.as_ref() {
+            Some(l) => Some(l),
+            None => self.sources.as_deref().map(|s| s as _),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace.as_ref()
     }
 }
 
 /// error + <file + line + column>
+///
+/// Built by [`SimpleMsg::new`] or `d!`, more fields may be added.
 #[derive(Debug)]
+#[non_exhaustive]
 pub struct SimpleMsg<E: Debug + Display + Send + 'static> {
     /// actual error
     pub err: E,
@@ -353,6 +1028,14 @@ pub struct SimpleMsg<E: Debug + Display + Send + 'static> {
     pub line: u32,
     /// column number
     pub column: u32,
+    /// machine-readable error code, e.g. an exit code or HTTP status
+    pub code: Option<i32>,
+    /// key-value context, e.g. request id, host, file path
+    pub fields: Vec<(&'static str, String)>,
+    /// the thread which built the error, see [`set_record_thread`]
+    pub thread: Option<String>,
+    /// the span stack when the error was built, see [`enter_span`]
+    pub spans: Vec<String>,
 }
 
 impl<E: Debug + Display + Send + 'static> SimpleMsg<E> {
@@ -364,38 +1047,107 @@ impl<E: Debug + Display + Send + 'static> SimpleMsg<E> {
             file,
             line,
             column,
+            code: None,
+            fields: vec![],
+            // captured when the error is built, see `SimpleError::build`
+            thread: None,
+            spans: vec![],
         }
     }
+
+    /// attach an error code, see `d!(code = 404, "...")`
+    #[inline(always)]
+    pub fn with_code(mut self, code: i32) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// attach a context field, see `d!("msg"; key = value)`
+    #[inline(always)]
+    pub fn with_field(
+        mut self,
+        key: &'static str,
+        value: impl Display,
+    ) -> Self {
+        self.fields.push((key, value.to_string()));
+        self
+    }
+
+    /// `(file, line, column)`
+    #[inline(always)]
+    pub fn location(&self) -> (&'static str, u32, u32) {
+        (self.file, self.line, self.column)
+    }
+
+    // Record the thread and the spans, if not yet;
+    // only an error being built pays for it, not every `d!()`
+    #[cfg(feature = "std")]
+    fn capture_context(mut self) -> Self {
+        if self.thread.is_none() {
+            self.thread = current_thread();
+        }
+        if self.spans.is_empty() {
+            self.spans = current_spans();
+        }
+        self
+    }
 }
 
 impl<E: Debug + Display + Send + 'static> Display for SimpleMsg<E> {
-    #[cfg(feature = "ansi")]
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(
-            f,
-            "\x1b[01m{0}\x1b[00m{4}{5}\x1b[01mfile:\x1b[00m {1}{4}{5}\x1b[01mline:\x1b[00m {2}{4}{6}\x1b[01mcolumn:\x1b[00m {3}",
-            self.err,
-            self.file,
-            self.line,
-            self.column,
-            delimiter(),
-            pretty()[0],
-            pretty()[1]
-        )
+        LevelView {
+            msg: &self.err,
+            code: self.code,
+            fields: self
+                .fields
+                .iter()
+                .map(|(k, v)| (*k, v.as_str()))
+                .collect(),
+            spans: &self.spans,
+            thread: self.thread.as_deref(),
+            location: self.location(),
+        }
+        .fmt(f)
     }
+}
 
-    #[cfg(not(feature = "ansi"))]
+// "error msg" + "debug info" of one level, in the current style
+struct LevelView<'a> {
+    msg: &'a dyn Display,
+    code: Option<i32>,
+    fields: Vec<(&'a str, &'a str)>,
+    spans: &'a [String],
+    thread: Option<&'a str>,
+    location: (&'a str, u32, u32),
+}
+
+impl Display for LevelView<'_> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let style = log_style();
+        let [mid, last] = style.pretty();
+        let (b, e) = if style.ansi {
+            ("\x1b[01m", "\x1b[00m")
+        } else {
+            ("", "")
+        };
+        let d = style.delimiter();
+        write!(f, "{b}{}{e}", self.msg)?;
+        if let Some(code) = self.code {
+            write!(f, "{d}{mid}{b}code:{e} {code}")?;
+        }
+        for (k, v) in self.fields.iter() {
+            write!(f, "{d}{mid}{b}{k}:{e} {v}")?;
+        }
+        if !self.spans.is_empty() {
+            write!(f, "{d}{mid}{b}spans:{e} {}", self.spans.join(" > "))?;
+        }
+        if let Some(t) = self.thread {
+            write!(f, "{d}{mid}{b}thread:{e} {t}")?;
+        }
+        let (file, line, column) = self.location;
         write!(
             f,
-            "{0}{4}{5}file: {1}{4}{5}line: {2}{4}{6}column: {3}",
-            self.err,
-            self.file,
-            self.line,
-            self.column,
-            delimiter(),
-            pretty()[0],
-            pretty()[1]
+            "{d}{mid}{b}file:{e} {file}{d}{mid}{b}line:{e} {line}{d}{last}{b}column:{e} {column}",
         )
     }
 }
@@ -409,7 +1161,7 @@ impl<E: Debug + Display + Send + 'static> From<SimpleMsg<E>>
 }
 
 #[inline(always)]
-#[cfg(target_os = "linux")]
+#[cfg(all(feature = "std", target_os = "linux"))]
 fn get_pidns(pid: u32) -> Result<String> {
     std::fs::read_link(format!("/proc/{pid}/ns/pid"))
         .c(crate::d!())
@@ -422,7 +1174,7 @@ fn get_pidns(pid: u32) -> Result<String> {
 }
 
 #[inline(always)]
-#[cfg(not(target_os = "linux"))]
+#[cfg(all(feature = "std", not(target_os = "linux")))]
 // the Result wrapper is required for signature parity
 // with the Linux implementation above
 #[allow(clippy::unnecessary_wraps)]
@@ -430,49 +1182,28 @@ fn get_pidns(_pid: u32) -> Result<String> {
     Ok("NULL".to_owned())
 }
 
-#[cfg(not(feature = "compact"))]
-const fn delimiter() -> &'static str {
-    "\n"
-}
-
-#[cfg(feature = "compact")]
-const fn delimiter() -> &'static str {
-    " 》"
-}
-
-#[cfg(not(feature = "compact"))]
-const fn indent() -> &'static str {
-    "    "
-}
-
-#[cfg(feature = "compact")]
-const fn indent() -> &'static str {
-    ""
-}
-
-#[cfg(all(not(feature = "compact"), feature = "ansi"))]
-const fn pretty() -> [&'static str; 2] {
-    ["├──", "└──"]
-}
-
-#[cfg(all(not(feature = "compact"), not(feature = "ansi")))]
-const fn pretty() -> [&'static str; 2] {
-    ["|--", "`--"]
-}
-
-#[cfg(feature = "compact")]
-const fn pretty() -> [&'static str; 2] {
-    ["", ""]
+#[cfg(feature = "std")]
+fn generate_log_header(ns: &str, pid: u32) -> String {
+    let (b, e) = if log_style().ansi {
+        ("\x1b[31;01m", "\x1b[00m")
+    } else {
+        ("", "")
+    };
+    format!(
+        "\n{b}# {time} [pid: {pid}] [pidns: {ns}]{ctx}{e}",
+        time = style::log_time(std::time::SystemTime::now()),
+        ctx = header_ctx(),
+    )
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use std::process;
 
+    #[cfg(feature = "std")]
     #[test]
     fn t_get_pidns() {
-        let ns_name = crate::pnk!(get_pidns(process::id()));
+        let ns_name = crate::pnk!(get_pidns(std::process::id()));
         assert!(1 < ns_name.len());
     }
 
@@ -505,6 +1236,144 @@ mod test {
         assert_eq!(e2.lowest_type_id(), TypeId::of::<&str>());
     }
 
+    #[test]
+    fn t_runtime_style() {
+        let e: Box<dyn RucError> =
+            SimpleError::new(SimpleMsg::new("***", "/tmp/xx.rs", 9, 90), None)
+                .into();
+        let origin = log_style();
+
+        set_log_style(LogStyle {
+            ansi: false,
+            compact: true,
+        });
+        let s = e.generate_log(None);
+        assert!(s.contains(" 》file: /tmp/xx.rs"));
+        assert!(!s.contains('\x1b'));
+
+        set_log_style(LogStyle {
+            ansi: true,
+            compact: false,
+        });
+        let s = e.generate_log(None);
+        assert!(s.contains("\n├──\x1b[01mfile:"));
+
+        set_log_style(origin);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn t_backtrace() {
+        let e1 = SimpleError::new(SimpleMsg::new("root", "f", 1, 1), None);
+        let has_bt = e1.backtrace().is_some();
+        let e2 = SimpleError::new(
+            SimpleMsg::new("cause", "f", 2, 2),
+            Some(Box::new(e1)),
+        );
+        // only the lowest level captures
+        assert!(e2.backtrace().is_none());
+        assert_eq!(has_bt, e2.get_lowest_err().backtrace().is_some());
+        assert_eq!(has_bt, e2.stringify_chain(None).contains("Backtrace:"));
+
+        let bt = Backtrace::force_capture();
+        let e3 = SimpleError {
+            msg: SimpleMsg::new("root", "f", 1, 1),
+            cause: None,
+            backtrace: Some(bt),
+            origin: None,
+            sources: None,
+        };
+        assert!(e3.stringify_chain(None).contains("Backtrace:"));
+    }
+
+    #[test]
+    fn t_codes() {
+        let e = Err::<(), _>(crate::eg!(code = 2, "root: {}", 1))
+            .c(crate::d!())
+            .c(crate::d!(code = 404, "not found: {}", "x"))
+            .c(crate::d!("top"))
+            .unwrap_err();
+        assert_eq!(e.top_code(), None);
+        assert_eq!(e.code(), Some(404));
+        assert_eq!(e.codes(), [404, 2]);
+        assert_eq!(e.get_lowest_msg(), "root: 1");
+
+        let chain = e.stringify_chain(None);
+        assert!(chain.contains("code:"));
+        assert!(chain.contains(" 404"));
+
+        let m = crate::d!(code = -1);
+        assert_eq!((m.err.as_str(), m.code), ("", Some(-1)));
+        let m = crate::d!(code = 7, "{}", 8).with_code(9);
+        assert_eq!((m.err.as_str(), m.code), ("8", Some(9)));
+    }
+
+    #[test]
+    fn t_fields() {
+        let path = "/etc/x.toml";
+        let e = Err::<(), _>(crate::eg!("denied"; uid = 1000))
+            .c(crate::d!("open {}", "cfg"; path = path, retry = 3,))
+            .c(crate::d!(code = 5, "load"; host = "h1"))
+            .c(crate::d!(; empty = ""))
+            .unwrap_err();
+        assert_eq!(
+            e.fields().collect::<Vec<_>>(),
+            [
+                ("empty", ""),
+                ("host", "h1"),
+                ("path", path),
+                ("retry", "3"),
+                ("uid", "1000")
+            ]
+        );
+        assert_eq!(e.code(), Some(5));
+        assert!(e.stringify_chain(None).contains(path));
+        assert!(
+            e.cause().unwrap().cause().unwrap().get_top_msg() == "open cfg"
+        );
+    }
+
+    #[test]
+    fn t_render_policy() {
+        let mut r: Result<()> = Err(crate::eg!("root"));
+        for _ in 0..5 {
+            r = r.c(SimpleMsg::new("retry", "f", 1, 1));
+        }
+        let e = r.c(crate::d!("top {}", "x".repeat(20))).unwrap_err();
+
+        let all = e.stringify_chain_with(None, RenderPolicy::default());
+        assert_eq!(all.matches("Caused By: ").count(), 6);
+
+        let collapsed = e.stringify_chain_with(
+            None,
+            RenderPolicy {
+                collapse_repeats: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(collapsed.matches("Caused By: ").count(), 2);
+        assert!(collapsed.contains("(repeated 5 times)"));
+
+        let capped = e.stringify_chain_with(
+            None,
+            RenderPolicy {
+                max_depth: Some(3),
+                max_msg_len: Some(6),
+                ..Default::default()
+            },
+        );
+        assert_eq!(capped.matches("Caused By: ").count(), 2);
+        assert!(capped.contains("... 4 levels elided ..."));
+        assert!(capped.contains("root"));
+        assert!(capped.contains("top xx...(18 chars truncated)"));
+
+        assert_eq!(truncate_msg("abc", 3), None);
+        assert_eq!(
+            truncate_msg("错误信息", 2).unwrap(),
+            "错误...(2 chars truncated)"
+        );
+    }
+
     #[test]
     fn t_error_chain_ids() {
         let e1 = SimpleError::new(SimpleMsg::new("root", "f", 1, 1), None);
@@ -543,6 +1412,149 @@ mod test {
         assert!(None::<u8>.c_with(|| crate::d!("lazy ctx")).is_err());
     }
 
+    #[test]
+    fn t_downcast() {
+        #[derive(Debug, PartialEq)]
+        enum Custom {
+            Busy,
+        }
+        impl Display for Custom {
+            fn fmt(
+                &self,
+                f: &mut core::fmt::Formatter<'_>,
+            ) -> core::fmt::Result {
+                write!(f, "{:?}", self)
+            }
+        }
+
+        let not_found = std::io::Error::from(std::io::ErrorKind::NotFound);
+        let e = Err::<(), _>(not_found)
+            .c(crate::d!("open"))
+            .c(crate::d!("load"))
+            .c(SimpleMsg::new(Custom::Busy, "f", 1, 1))
+            .unwrap_err();
+
+        let io_err = e.downcast_ref::<std::io::Error>().unwrap();
+        assert_eq!(io_err.kind(), std::io::ErrorKind::NotFound);
+        assert!(e.lowest_is_type(io_err));
+        assert!(e.get_lowest_err().type_name().contains("io::error::Error"));
+
+        assert_eq!(e.find::<Custom>(), Some(&Custom::Busy));
+        assert_eq!(e.find::<String>().map(|s| s.as_str()), Some("load"));
+        assert!(e.find::<u8>().is_none());
+        assert!(e.downcast_ref::<Custom>().is_none());
+    }
+
+    #[derive(Debug)]
+    struct Layer(&'static str, Option<Box<Layer>>);
+
+    impl Display for Layer {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl core::error::Error for Layer {
+        fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+            self.1.as_deref().map(|l| l as _)
+        }
+    }
+
+    #[test]
+    fn t_std_source() {
+        let e = Err::<(), _>(crate::eg!("root"))
+            .c(crate::d!("middle"))
+            .c(crate::d!("top"))
+            .unwrap_err();
+        let e: Box<dyn core::error::Error> = e.into();
+        let msgs = core::iter::successors(e.source(), |e| e.source())
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(msgs, ["middle", "root"]);
+    }
+
+    #[test]
+    fn t_chain_std_sources() {
+        let layers = || Layer("outer", Some(Box::new(Layer("inner", None))));
+
+        // sources of a concrete std error, by `c_src`,
+        // the error itself stays the lowest level
+        let e = Err::<(), _>(layers()).c_src(crate::d!("top")).unwrap_err();
+        assert_eq!(e.type_ids().len(), 2);
+        assert_eq!(e.get_lowest_msg(), "outer");
+        assert_eq!(e.get_lowest_err().top_sources(), ["inner"]);
+        assert_eq!(e.downcast_ref::<Layer>().map(|l| l.0), Some("outer"));
+        assert!(e.stringify_chain(None).contains("Caused By: inner"));
+
+        // sources of type-erased std errors, by `c`
+        type BoxErr = Box<dyn core::error::Error + Send + Sync>;
+        let boxed: BoxErr = Box::new(layers());
+        let e = Err::<(), _>(boxed).c(crate::d!("top")).unwrap_err();
+        assert_eq!(e.type_ids().len(), 2);
+        assert_eq!(e.get_lowest_msg(), "outer");
+        assert_eq!(e.get_lowest_err().top_sources(), ["inner"]);
+        assert!(e.downcast_ref::<BoxErr>().is_some());
+        let e: Box<dyn core::error::Error> = e.into();
+        let msgs = core::iter::successors(e.source(), |e| e.source())
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(msgs, ["outer", "inner"]);
+
+        // `Send` only
+        let boxed: Box<dyn core::error::Error + Send> = Box::new(layers());
+        let e = Err::<(), _>(boxed).c(crate::d!("top")).unwrap_err();
+        assert_eq!(e.get_lowest_msg(), "outer");
+        assert_eq!(e.get_lowest_err().top_sources(), ["inner"]);
+
+        // ruc errors are kept as-is
+        let e = Err::<(), _>(crate::eg!("root"))
+            .c_src(crate::d!("top"))
+            .unwrap_err();
+        assert_eq!(e.type_ids().len(), 2);
+    }
+
+    #[derive(Debug)]
+    #[cfg(feature = "std")]
+    struct Outer(std::io::Error);
+
+    #[cfg(feature = "std")]
+    impl Display for Outer {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(f, "outer")
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl core::error::Error for Outer {
+        fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn t_chain_nested_io_error() {
+        use std::io::{Error, ErrorKind};
+
+        let io = Error::new(ErrorKind::NotFound, Outer(Error::other("root")));
+        let e = Err::<(), _>(io)
+            .c(crate::d!("mid"))
+            .c(crate::d!("top"))
+            .unwrap_err();
+        assert_eq!(e.type_ids().len(), 3);
+        assert_eq!(
+            e.downcast_ref::<Error>().map(|e| e.kind()),
+            Some(ErrorKind::NotFound)
+        );
+        assert!(e.lowest_is_type(&Error::other("")));
+        assert_eq!(e.lowest_type_id(), TypeId::of::<Error>());
+        assert_eq!(e.get_lowest_msg(), "outer");
+        assert_eq!(e.get_lowest_err().top_sources(), ["root"]);
+
+        let s = e.stringify_chain(None);
+        assert!(s.find("outer") < s.find("Caused By: root"));
+    }
+
     #[test]
     fn t_chain_str_error() {
         let r: core::result::Result<(), &str> = Err("str error");
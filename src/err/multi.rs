@@ -0,0 +1,235 @@
+//!
+//! # Aggregated errors
+//!
+//! Many independent failures(config validation, fan-out over hosts, etc.)
+//! collected into one [`RucError`], each kept as a full chain.
+//!
+
+use super::{Result, RucError, SimpleMsg};
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{
+    any::TypeId,
+    fmt::{Debug, Display},
+    panic::Location,
+};
+
+/// An error holding N independent error chains as its branches
+#[derive(Debug)]
+pub struct MultiError {
+    msg: SimpleMsg<String>,
+    errors: Vec<Box<dyn RucError>>,
+}
+
+impl MultiError {
+    /// Create a new error with the given message and branches.
+    #[inline(always)]
+    pub fn new(
+        msg: SimpleMsg<String>,
+        errors: Vec<Box<dyn RucError>>,
+    ) -> Self {
+        MultiError { msg, errors }
+    }
+
+    /// Add a branch
+    #[inline(always)]
+    pub fn push(&mut self, e: Box<dyn RucError>) {
+        self.errors.push(e);
+    }
+
+    /// All the branches
+    #[inline(always)]
+    pub fn errors(&self) -> &[Box<dyn RucError>] {
+        &self.errors
+    }
+
+    /// Number of branches
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Check if there is no branch
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Consume self, return all the branches
+    #[inline(always)]
+    pub fn into_errors(self) -> Vec<Box<dyn RucError>> {
+        self.errors
+    }
+}
+
+impl Display for MultiError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.generate_log(None))
+    }
+}
+
+impl core::error::Error for MultiError {}
+
+impl From<MultiError> for Box<dyn RucError> {
+    fn from(e: MultiError) -> Box<dyn RucError> {
+        Box::new(e)
+    }
+}
+
+impl RucError for MultiError {
+    fn type_id(&self) -> TypeId {
+        TypeId::of::<MultiError>()
+    }
+
+    fn type_name(&self) -> &str {
+        core::any::type_name::<MultiError>()
+    }
+
+    fn location(&self) -> Option<(&str, u32, u32)> {
+        Some(self.msg.location())
+    }
+
+    fn top_code(&self) -> Option<i32> {
+        self.msg.code
+    }
+
+    fn top_fields(&self) -> Vec<(&str, &str)> {
+        self.msg
+            .fields
+            .iter()
+            .map(|(k, v)| (*k, v.as_str()))
+            .collect()
+    }
+
+    fn top_spans(&self) -> Vec<&str> {
+        self.msg.spans.iter().map(|s| s.as_str()).collect()
+    }
+
+    fn top_thread(&self) -> Option<&str> {
+        self.msg.thread.as_deref()
+    }
+
+    fn get_top_msg(&self) -> String {
+        self.msg.err.clone()
+    }
+
+    // branches are not causes, so this is the lowest level
+    fn get_lowest_msg(&self) -> String {
+        self.msg.err.clone()
+    }
+
+    fn get_lowest_err(&self) -> &dyn RucError {
+        self
+    }
+
+    fn get_top_msg_with_dbginfo(&self) -> String {
+        self.msg.to_string()
+    }
+
+    fn branches(&self) -> &[Box<dyn RucError>] {
+        &self.errors
+    }
+}
+
+/// Collect `Result`s into `Result<Vec<T>>`,
+/// keeping ALL the errors as branches of a [`MultiError`],
+/// instead of stopping at the first one.
+#[track_caller]
+pub fn collect_all<T, I>(iter: I) -> Result<Vec<T>>
+where
+    I: IntoIterator<Item = Result<T>>,
+{
+    let loc = Location::caller();
+    let mut oks = vec![];
+    let mut errs = vec![];
+    for r in iter {
+        match r {
+            Ok(v) => oks.push(v),
+            Err(e) => errs.push(e),
+        }
+    }
+    if errs.is_empty() {
+        Ok(oks)
+    } else {
+        let msg = format!("{} errors occurred", errs.len());
+        let msg = SimpleMsg::new(msg, loc.file(), loc.line(), loc.column());
+        Err(MultiError::new(msg, errs).into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::*;
+
+    fn check(i: u32) -> Result<u32> {
+        if i.is_multiple_of(2) {
+            Ok(i)
+        } else {
+            Err(eg!("odd: {}", i)).c(d!("check"))
+        }
+    }
+
+    #[test]
+    fn t_collect_all() {
+        assert_eq!(collect_all([0, 2, 4].map(check)).unwrap(), [0, 2, 4]);
+
+        let e = collect_all((0..5).map(check)).unwrap_err();
+        assert_eq!(e.branches().len(), 2);
+        assert_eq!(e.get_top_msg(), "2 errors occurred");
+        assert_eq!(e.location().unwrap().0, file!());
+        assert_eq!(e.branches()[1].get_lowest_msg(), "odd: 3");
+
+        // branches are not causes
+        assert_eq!(e.type_ids().len(), 1);
+        assert!(e.contains_type(&String::new()));
+        assert!(e.contains_type(&MultiError::new(d!(), vec![])));
+
+        let another = eg!("odd: 1");
+        assert!(e.msg_has_overlap(another.as_ref()));
+        assert!(another.msg_has_overlap(e.as_ref()));
+        assert!(!e.msg_has_overlap(eg!("odd: 2").as_ref()));
+    }
+
+    #[test]
+    fn t_lowest_of_branches() {
+        let e = collect_all(["1", "x"].map(|s| s.parse::<u8>().c(d!())))
+            .unwrap_err();
+        // the `MultiError` itself, for all of the `lowest_*` API
+        assert_eq!(e.lowest_type_id(), TypeId::of::<MultiError>());
+        assert!(e.get_lowest_err().type_name().ends_with("MultiError"));
+        assert!(!e.lowest_is_type(&"".parse::<u8>().unwrap_err()));
+        assert!(e.downcast_ref::<core::num::ParseIntError>().is_none());
+
+        // reachable by the branch-aware ones
+        assert!(e.contains_type(&"".parse::<u8>().unwrap_err()));
+        assert!(e.find::<core::num::ParseIntError>().is_some());
+    }
+
+    #[test]
+    fn t_render_branches() {
+        let e = collect_all((0..4).map(check))
+            .c(d!("validate"))
+            .unwrap_err();
+        let s = e.stringify_chain(None);
+        assert!(s.contains("Caused By: "));
+        assert!(s.contains("2 errors occurred"));
+        assert!(s.contains("Branch [1/2]: "));
+        assert!(s.contains("Branch [2/2]: "));
+        assert!(s.contains("odd: 1"));
+        assert!(s.contains("odd: 3"));
+        assert!(s.find("Branch [1/2]") < s.find("odd: 1"));
+        assert!(s.find("odd: 1") < s.find("Branch [2/2]"));
+
+        let mut m = MultiError::new(d!("empty"), vec![]);
+        assert!(m.is_empty());
+        m.push(eg!());
+        assert_eq!(m.len(), 1);
+        assert_eq!(m.into_errors().len(), 1);
+    }
+}
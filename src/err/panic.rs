@@ -0,0 +1,173 @@
+//!
+//! # Panic hook
+//!
+//! Render ordinary panics(`unwrap`, index out of bounds, etc.)
+//! in the same layout as error chains, through the global [`LogSink`](super::LogSink),
+//! see [`install_panic_hook`].
+//!
+
+use super::{
+    LevelView, LogLevel, RucError, capture_backtrace, current_spans, emit,
+    thread_label,
+};
+use core::{
+    any::{Any, TypeId},
+    cell::Cell,
+    fmt::Display,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use std::{backtrace::Backtrace, panic::Location};
+
+// nothing is marked before the hook is installed,
+// or no one would clear the mark
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static REPORTED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Install a panic hook in front of the current one,
+/// every panic is printed as a `PANIC` log with its location,
+/// along with the header(time, pid, pidns) of other logs,
+/// then passed on to the previous hook
+/// (the default one prints the panic message to stderr).
+///
+/// Panics raised by `pnk!`, `die!` and [`RucError::print_die`]
+/// are not printed again, they have been logged before panicking.
+pub fn install_panic_hook() {
+    let prev = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if !REPORTED.replace(false) {
+            let e = PanicError::new(info.payload(), info.location());
+            emit(LogLevel::Error, &e.generate_log(Some("PANIC")), Some(&e));
+        }
+        prev(info);
+    }));
+    INSTALLED.store(true, Ordering::Release);
+}
+
+/// Mark the next panic of the current thread as already logged,
+/// only if the hook is installed
+#[doc(hidden)]
+pub fn __mark_reported() {
+    if INSTALLED.load(Ordering::Acquire) {
+        REPORTED.set(true);
+    }
+}
+
+// A panic seen by the hook, as a single-level chain
+#[derive(Debug)]
+struct PanicError {
+    msg: String,
+    file: String,
+    line: u32,
+    column: u32,
+    thread: String,
+    spans: Vec<String>,
+    backtrace: Option<Backtrace>,
+}
+
+impl PanicError {
+    fn new(payload: &(dyn Any + Send), loc: Option<&Location<'_>>) -> Self {
+        let msg = if let Some(s) = payload.downcast_ref::<&str>() {
+            (*s).to_owned()
+        } else if let Some(s) = payload.downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "Box<dyn Any>".to_owned()
+        };
+        PanicError {
+            msg,
+            file: loc.map(|l| l.file().to_owned()).unwrap_or_default(),
+            line: loc.map(|l| l.line()).unwrap_or_default(),
+            column: loc.map(|l| l.column()).unwrap_or_default(),
+            thread: thread_label(),
+            spans: current_spans(),
+            backtrace: capture_backtrace(),
+        }
+    }
+}
+
+impl Display for PanicError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.generate_log(Some("PANIC")))
+    }
+}
+
+impl RucError for PanicError {
+    fn type_id(&self) -> TypeId {
+        TypeId::of::<PanicError>()
+    }
+
+    fn type_name(&self) -> &str {
+        "panic"
+    }
+
+    fn location(&self) -> Option<(&str, u32, u32)> {
+        Some((&self.file, self.line, self.column))
+    }
+
+    fn top_spans(&self) -> Vec<&str> {
+        self.spans.iter().map(|s| s.as_str()).collect()
+    }
+
+    fn top_thread(&self) -> Option<&str> {
+        Some(&self.thread)
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace.as_ref()
+    }
+
+    fn get_top_msg(&self) -> String {
+        self.msg.clone()
+    }
+
+    fn get_lowest_msg(&self) -> String {
+        self.msg.clone()
+    }
+
+    fn get_lowest_err(&self) -> &dyn RucError {
+        self
+    }
+
+    fn get_top_msg_with_dbginfo(&self) -> String {
+        LevelView {
+            msg: &self.msg,
+            code: None,
+            fields: vec![],
+            spans: &self.spans,
+            thread: Some(&self.thread),
+            location: (&self.file, self.line, self.column),
+        }
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn t_panic_error() {
+        let loc = Location::caller();
+        let e = PanicError::new(&"index out of bounds", Some(loc));
+        assert_eq!(e.get_top_msg(), "index out of bounds");
+        assert_eq!(e.location().unwrap().0, file!());
+        assert!(e.top_thread().unwrap().contains('#'));
+
+        let s = e.generate_log(Some("PANIC"));
+        assert!(s.contains("PANIC: "));
+        assert!(s.contains("index out of bounds"));
+        assert!(s.contains("pidns"));
+
+        let e = PanicError::new(&String::from("boom"), None);
+        assert_eq!(e.get_top_msg(), "boom");
+        assert_eq!(PanicError::new(&42, None).get_top_msg(), "Box<dyn Any>");
+
+        // see `tests/panic_hook.rs` for the hook itself
+        REPORTED.set(true);
+        assert!(REPORTED.replace(false));
+        assert!(!REPORTED.get());
+    }
+}
@@ -0,0 +1,271 @@
+//!
+//! # Errors across process boundaries
+//!
+//! A `Box<dyn RucError>` can not be serialized directly,
+//! [`WireError`] is its serializable form, encodable by
+//! `ende::json`, `ende::msgpack`, etc.
+//!
+//! The receiver turns it back into a `Box<dyn RucError>`,
+//! whose levels are [`RemoteError`]s.
+//!
+
+use super::{LevelRecord, LevelView, Link, RucError, SourceMsg};
+use core::{any::TypeId, fmt::Display};
+use serde::{Deserialize, Serialize};
+
+/// The serializable form of a whole error chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WireError {
+    /// levels of the error chain, from top to bottom
+    pub chain: Vec<LevelRecord>,
+}
+
+impl WireError {
+    pub(super) fn new(e: &dyn RucError) -> Self {
+        // backtraces are only meaningful in the local process
+        WireError {
+            chain: LevelRecord::chain(e, false),
+        }
+    }
+}
+
+impl From<WireError> for Box<dyn RucError> {
+    /// An empty chain is converted into a single level
+    /// with an empty message.
+    fn from(w: WireError) -> Self {
+        let mut levels = w.chain;
+        if levels.is_empty() {
+            levels.push(LevelRecord::default());
+        }
+        levels
+            .into_iter()
+            .rev()
+            .fold(None, |cause, mut level| {
+                let branches =
+                    level.branches.drain(..).map(Into::into).collect();
+                let sources = SourceMsg::chain(level.sources.clone());
+                Some(Box::new(RemoteError {
+                    level,
+                    cause: cause.map(Link),
+                    branches,
+                    sources,
+                }) as Box<dyn RucError>)
+            })
+            .unwrap() // at least one level
+    }
+}
+
+/// One level of an error chain received from another process,
+/// see [`WireError`]
+#[derive(Debug)]
+pub struct RemoteError {
+    level: LevelRecord,
+    cause: Option<Link>,
+    branches: Vec<Box<dyn RucError>>,
+    // `level.sources`, as seen by `Error::source`
+    sources: Option<Box<SourceMsg>>,
+}
+
+impl Display for RemoteError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.generate_log(None))
+    }
+}
+
+impl std::error::Error for RemoteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.std_source()
+    }
+}
+
+impl RucError for RemoteError {
+    fn type_id(&self) -> TypeId {
+        TypeId::of::<RemoteError>()
+    }
+
+    /// type name of the original error, in the remote process
+    fn type_name(&self) -> &str {
+        &self.level.type_name
+    }
+
+    fn is_remote(&self) -> bool {
+        true
+    }
+
+    fn location(&self) -> Option<(&str, u32, u32)> {
+        if self.level.file.is_empty() {
+            None
+        } else {
+            Some((&self.level.file, self.level.line, self.level.column))
+        }
+    }
+
+    fn top_code(&self) -> Option<i32> {
+        self.level.code
+    }
+
+    fn top_fields(&self) -> Vec<(&str, &str)> {
+        self.level
+            .fields
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect()
+    }
+
+    fn top_spans(&self) -> Vec<&str> {
+        self.level.spans.iter().map(|s| s.as_str()).collect()
+    }
+
+    fn top_thread(&self) -> Option<&str> {
+        self.level.thread.as_deref()
+    }
+
+    fn top_sources(&self) -> Vec<&str> {
+        self.level.sources.iter().map(|s| s.as_str()).collect()
+    }
+
+    fn get_top_msg(&self) -> String {
+        self.level.msg.clone()
+    }
+
+    fn get_lowest_msg(&self) -> String {
+        self.get_lowest_err().get_top_msg()
+    }
+
+    fn get_lowest_err(&self) -> &dyn RucError {
+        match self.cause() {
+            Some(c) => c.get_lowest_err(),
+            None => self,
+        }
+    }
+
+    fn get_top_msg_with_dbginfo(&self) -> String {
+        let mut fields = vec![("remote", self.level.type_name.as_str())];
+        fields.extend(self.top_fields());
+        let (file, line, column) = self.location().unwrap_or_default();
+        LevelView {
+            msg: &self.level.msg,
+            code: self.level.code,
+            fields,
+            spans: &self.level.spans,
+            thread: self.level.thread.as_deref(),
+            location: (file, line, column),
+        }
+        .to_string()
+    }
+
+    fn cause(&self) -> Option<&dyn RucError> {
+        self.cause.as_ref().map(|l| l.0.as_ref())
+    }
+
+    fn branches(&self) -> &[Box<dyn RucError>] {
+        &self.branches
+    }
+
+    fn std_source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self.cause.as_ref() {
+            Some(l) => Some(l),
+            None => self.sources.as_deref().map(|s| s as _),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::*;
+
+    #[derive(Debug)]
+    struct Outer(std::io::Error);
+
+    impl Display for Outer {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(f, "outer")
+        }
+    }
+
+    impl std::error::Error for Outer {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    fn chain() -> Box<dyn RucError> {
+        let branches =
+            collect_all([1, 2].map(|i| Err::<(), _>(eg!("host-{} down", i))))
+                .unwrap_err();
+        Err::<(), _>(branches)
+            .c(d!(code = 503, "sync"; peer = "10.0.0.1"))
+            .c(d!("top"))
+            .unwrap_err()
+    }
+
+    fn check(w: WireError) {
+        let e: Box<dyn RucError> = w.into();
+        assert!(e.is_remote());
+        assert_eq!(e.get_top_msg(), "top");
+        assert_eq!(e.codes(), [503]);
+        assert_eq!(e.fields().collect::<Vec<_>>(), [("peer", "10.0.0.1")]);
+        assert_eq!(e.location().unwrap().0, file!());
+        assert_eq!(e.get_lowest_msg(), "2 errors occurred");
+
+        let lowest = e.get_lowest_err();
+        assert!(lowest.type_name().ends_with("MultiError"));
+        assert_eq!(lowest.branches().len(), 2);
+        assert_eq!(lowest.branches()[1].get_top_msg(), "host-2 down");
+
+        let s = e.stringify_chain(None);
+        assert!(s.contains("remote:"));
+        assert!(s.contains("Branch [2/2]: "));
+        assert!(s.contains("host-1 down"));
+    }
+
+    #[test]
+    fn t_wire_roundtrip() {
+        let w = chain().to_wire();
+        assert_eq!(w.chain.len(), 3);
+        assert_eq!(w.chain[2].branches.len(), 2);
+        check(w.clone());
+
+        // one level per source, as for local chains
+        let e: Box<dyn RucError> = w.clone().into();
+        let e: Box<dyn std::error::Error> = e.into();
+        let msgs = core::iter::successors(e.source(), |e| e.source())
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(msgs, ["sync", "2 errors occurred"]);
+
+        // remote errors can be forwarded again
+        let e: Box<dyn RucError> = w.clone().into();
+        assert_eq!(e.to_wire(), w);
+
+        // the sources of a foreign error
+        let w = Err::<(), _>(Outer(std::io::Error::other("root")))
+            .c_src(d!("top"))
+            .unwrap_err()
+            .to_wire();
+        assert_eq!(w.chain[1].sources, ["root"]);
+        let e: Box<dyn RucError> = w.into();
+        assert_eq!(e.get_lowest_err().top_sources(), ["root"]);
+        assert!(e.stringify_chain(None).contains("Caused By: root"));
+
+        let e: Box<dyn RucError> = WireError { chain: vec![] }.into();
+        assert_eq!(e.get_top_msg(), "");
+        assert!(e.cause().is_none());
+    }
+
+    #[cfg(feature = "ende_json")]
+    #[test]
+    fn t_wire_json() {
+        let bytes =
+            crate::ende::json::json_encode(&chain().to_wire()).unwrap();
+        check(crate::ende::json::json_decode(&bytes).unwrap());
+    }
+
+    #[cfg(feature = "ende_msgpack")]
+    #[test]
+    fn t_wire_msgpack() {
+        let bytes = crate::ende::msgpack::encode(&chain().to_wire()).unwrap();
+        check(crate::ende::msgpack::decode(&bytes).unwrap());
+    }
+}
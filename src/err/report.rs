@@ -0,0 +1,191 @@
+//!
+//! # Structured report
+//!
+//! Serializable form of an error chain,
+//! for log pipelines that ingest JSON instead of plain text.
+//!
+
+use super::{PID, PID_NS, RucError, WireError, current_spans, current_thread};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The log header and every level of an error chain
+#[derive(Debug, Clone, Serialize)]
+pub struct LogRecord {
+    /// formatted local datetime, same as the text header
+    pub time: String,
+    /// UTC-timestamp in seconds
+    pub timestamp: u64,
+    /// process id
+    pub pid: u32,
+    /// pid namespace
+    pub pidns: String,
+    /// `ERROR`, `INFO`, etc.
+    pub prefix: String,
+    /// the printing thread, if recorded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread: Option<String>,
+    /// the span stack of the printing thread
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub spans: Vec<String>,
+    /// levels of the error chain, from top to bottom
+    pub chain: Vec<LevelRecord>,
+}
+
+impl LogRecord {
+    pub(super) fn new(prefix: Option<&str>, chain: Vec<LevelRecord>) -> Self {
+        let now = std::time::SystemTime::now();
+        LogRecord {
+            time: super::style::log_time(now),
+            timestamp: now
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            pid: *PID,
+            pidns: PID_NS.clone(),
+            prefix: prefix.unwrap_or("ERROR").to_owned(),
+            thread: current_thread(),
+            spans: current_spans(),
+            chain,
+        }
+    }
+}
+
+/// One level of an error chain, also the level of a [`WireError`]
+///
+/// NOTE: no field is skipped on serializing,
+/// so that positional formats like msgpack work.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LevelRecord {
+    /// error message of this level
+    pub msg: String,
+    /// file path, empty if not recorded
+    pub file: String,
+    /// line number, `0` if not recorded
+    pub line: u32,
+    /// column number, `0` if not recorded
+    pub column: u32,
+    /// type name of the original error of this level
+    pub type_name: String,
+    /// error code of this level, if attached
+    pub code: Option<i32>,
+    /// context fields of this level
+    pub fields: BTreeMap<String, String>,
+    /// the span stack when this level was created
+    pub spans: Vec<String>,
+    /// the thread which created this level, if recorded
+    pub thread: Option<String>,
+    /// the `Error::source` chain of the original error of this level
+    pub sources: Vec<String>,
+    /// backtrace captured by the lowest level, if enabled
+    pub backtrace: Option<String>,
+    /// independent errors aggregated by this level, see `MultiError`
+    pub branches: Vec<WireError>,
+}
+
+impl LevelRecord {
+    // the levels of `e` from top to bottom
+    pub(super) fn chain(e: &dyn RucError, backtrace: bool) -> Vec<Self> {
+        core::iter::successors(Some(e), |c| c.cause())
+            .map(|c| LevelRecord::new(c, backtrace))
+            .collect()
+    }
+
+    fn new(e: &dyn RucError, backtrace: bool) -> Self {
+        let (file, line, column) = e.location().unwrap_or_default();
+        LevelRecord {
+            msg: e.get_top_msg(),
+            file: file.to_owned(),
+            line,
+            column,
+            type_name: e.type_name().to_owned(),
+            code: e.top_code(),
+            fields: e
+                .top_fields()
+                .into_iter()
+                .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                .collect(),
+            spans: e.top_spans().into_iter().map(|s| s.to_owned()).collect(),
+            thread: e.top_thread().map(|t| t.to_owned()),
+            sources: e
+                .top_sources()
+                .into_iter()
+                .map(|s| s.to_owned())
+                .collect(),
+            backtrace: e
+                .backtrace()
+                .filter(|_| backtrace)
+                .map(|bt| bt.to_string()),
+            branches: e
+                .branches()
+                .iter()
+                .map(|b| WireError {
+                    chain: LevelRecord::chain(b.as_ref(), backtrace),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[test]
+    fn t_serialize_chain() {
+        let e = Err::<(), _>(eg!("root"))
+            .c(d!(code = 500, "middle"))
+            .c(d!("top"))
+            .unwrap_err();
+        let r = e.serialize_chain(Some("INFO"));
+        assert_eq!(r.prefix, "INFO");
+        assert_eq!(r.pid, std::process::id());
+        assert_eq!(r.chain.len(), 3);
+        assert_eq!(r.chain[0].msg, "top");
+        assert_eq!(r.chain[0].code, None);
+        assert_eq!(r.chain[1].code, Some(500));
+        assert_eq!(r.chain[2].msg, "root");
+        assert_eq!(r.chain[2].file, file!());
+        assert!(0 < r.chain[2].line);
+        assert!(r.chain[2].type_name.contains("String"));
+    }
+
+    #[test]
+    fn t_serialize_branches() {
+        let e =
+            collect_all([1, 2].map(|i| Err::<(), _>(eg!("host-{} down", i))))
+                .c(d!("sync"))
+                .unwrap_err();
+        let r = e.serialize_chain(None);
+        assert_eq!(r.chain.len(), 2);
+        assert!(r.chain[0].branches.is_empty());
+        let branches = &r.chain[1].branches;
+        assert_eq!(branches.len(), 2);
+        assert_eq!(branches[1].chain[0].msg, "host-2 down");
+    }
+
+    #[cfg(feature = "ende_json")]
+    #[test]
+    fn t_to_json() {
+        let e = Err::<(), _>("io failure")
+            .c(d!("read cfg"; path = "/tmp/x"))
+            .unwrap_err();
+        let v: serde_json::Value =
+            serde_json::from_str(&e.to_json(None)).unwrap();
+        assert_eq!(v["prefix"], "ERROR");
+        assert_eq!(v["chain"][0]["msg"], "read cfg");
+        assert_eq!(v["chain"][0]["fields"]["path"], "/tmp/x");
+        assert!(v["chain"][1]["fields"].as_object().unwrap().is_empty());
+        assert_eq!(v["chain"][1]["msg"], "io failure");
+        assert_eq!(v["chain"][1]["file"], file!());
+        assert!(v["timestamp"].as_u64().unwrap() > 0);
+        assert!(v["pidns"].is_string());
+        assert!(v["chain"][0]["branches"].as_array().unwrap().is_empty());
+
+        let e =
+            collect_all([Err::<(), _>(eg!("a")), Err(eg!("b"))]).unwrap_err();
+        let v: serde_json::Value =
+            serde_json::from_str(&e.to_json(None)).unwrap();
+        assert_eq!(v["chain"][0]["branches"][1]["chain"][0]["msg"], "b");
+    }
+}
@@ -0,0 +1,434 @@
+//!
+//! # Shared errors
+//!
+//! A `Box<dyn RucError>` is `Send` but neither `Sync` nor `Clone`,
+//! as `.c()` accepts errors which are only `Send`.
+//! [`SharedError`] is a cheaply cloneable `Send + Sync` handle
+//! to one chain, so that one failure can be handed out to many waiters.
+//!
+//! The chain is kept behind a lock: owned results(messages, type ids,
+//! codes, the rendered log, etc.) are forwarded, the levels themselves
+//! and the original errors are reached through [`SharedError::with`].
+//!
+//! Converted back into a `Box<dyn RucError>`, the last handle gives
+//! the original chain, any other one gives a copy of its levels,
+//! without the original error values and the backtrace.
+//!
+
+use super::{Link, RenderPolicy, RucError, SourceMsg, collect_top_msgs};
+use std::{
+    any::TypeId,
+    collections::BTreeSet,
+    fmt::Display,
+    sync::{Arc, Mutex},
+};
+
+/// An `Arc`-based error chain, cloning it is cheap
+#[derive(Debug, Clone)]
+pub struct SharedError(Arc<Inner>);
+
+#[derive(Debug)]
+struct Inner {
+    chain: Mutex<Box<dyn RucError>>,
+    // the messages below the top level, as seen by `Error::source`
+    sources: Option<Box<SourceMsg>>,
+}
+
+impl SharedError {
+    /// Check if two handles point to the same chain
+    #[inline(always)]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Run `f` on the chain itself, e.g. to walk its levels
+    /// or to `downcast_ref` its root cause.
+    ///
+    /// NOTE: the chain is locked within `f`,
+    /// do not use this handle or any of its clones there.
+    pub fn with<R>(&self, f: impl FnOnce(&dyn RucError) -> R) -> R {
+        let chain = self.0.chain.lock().unwrap_or_else(|e| e.into_inner());
+        f(chain.as_ref())
+    }
+}
+
+impl From<Box<dyn RucError>> for SharedError {
+    fn from(e: Box<dyn RucError>) -> Self {
+        let mut msgs = vec![];
+        let levels = core::iter::successors(Some(e.as_ref()), |c| c.cause());
+        for (i, c) in levels.enumerate() {
+            if 0 < i {
+                msgs.push(c.get_top_msg());
+            }
+            msgs.extend(c.top_sources().into_iter().map(|s| s.to_owned()));
+        }
+        SharedError(Arc::new(Inner {
+            chain: Mutex::new(e),
+            sources: SourceMsg::chain(msgs),
+        }))
+    }
+}
+
+impl From<SharedError> for Box<dyn RucError> {
+    /// The original chain if this is the last handle,
+    /// a copy of its levels otherwise
+    fn from(e: SharedError) -> Self {
+        match Arc::try_unwrap(e.0) {
+            Ok(inner) => {
+                inner.chain.into_inner().unwrap_or_else(|e| e.into_inner())
+            }
+            Err(arc) => SharedError(arc).with(Copied::chain),
+        }
+    }
+}
+
+impl Display for SharedError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.with(|e| Display::fmt(e, f))
+    }
+}
+
+impl core::error::Error for SharedError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        self.std_source()
+    }
+}
+
+// The handle is seen as a single level,
+// with the type, messages and rendering of the whole chain.
+impl RucError for SharedError {
+    fn type_id(&self) -> TypeId {
+        self.with(|e| e.type_id())
+    }
+
+    fn type_ids(&self) -> Vec<TypeId> {
+        self.with(|e| e.type_ids())
+    }
+
+    fn lowest_type_id(&self) -> TypeId {
+        self.with(|e| e.lowest_type_id())
+    }
+
+    fn msg_has_overlap(&self, another: &dyn RucError) -> bool {
+        // `another` may be a clone of this handle
+        let mut self_list = BTreeSet::new();
+        self.with(|e| collect_top_msgs(e, &mut self_list));
+        let mut another_list = BTreeSet::new();
+        collect_top_msgs(another, &mut another_list);
+        !self_list.is_disjoint(&another_list)
+    }
+
+    fn is_remote(&self) -> bool {
+        self.with(|e| e.is_remote())
+    }
+
+    fn top_code(&self) -> Option<i32> {
+        self.with(|e| e.top_code())
+    }
+
+    fn code(&self) -> Option<i32> {
+        self.with(|e| e.code())
+    }
+
+    fn codes(&self) -> Vec<i32> {
+        self.with(|e| e.codes())
+    }
+
+    fn get_top_msg(&self) -> String {
+        self.with(|e| e.get_top_msg())
+    }
+
+    fn get_lowest_msg(&self) -> String {
+        self.with(|e| e.get_lowest_msg())
+    }
+
+    /// The handle itself, the original error is reached by `with`
+    fn get_lowest_err(&self) -> &dyn RucError {
+        self
+    }
+
+    fn get_top_msg_with_dbginfo(&self) -> String {
+        self.with(|e| e.get_top_msg_with_dbginfo())
+    }
+
+    fn std_source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        self.0.sources.as_deref().map(|s| s as _)
+    }
+
+    fn stringify_chain_with(
+        &self,
+        prefix: Option<&str>,
+        policy: RenderPolicy,
+    ) -> String {
+        self.with(|e| e.stringify_chain_with(prefix, policy))
+    }
+
+    #[cfg(feature = "serde")]
+    fn serialize_chain(&self, prefix: Option<&str>) -> super::LogRecord {
+        self.with(|e| e.serialize_chain(prefix))
+    }
+
+    #[cfg(feature = "serde")]
+    fn to_wire(&self) -> super::WireError {
+        self.with(|e| e.to_wire())
+    }
+}
+
+// One level copied out of a chain which is still shared,
+// all but the original error value
+#[derive(Debug)]
+struct Copied {
+    type_id: TypeId,
+    type_name: String,
+    is_remote: bool,
+    location: Option<(String, u32, u32)>,
+    code: Option<i32>,
+    fields: Vec<(String, String)>,
+    spans: Vec<String>,
+    thread: Option<String>,
+    msg: String,
+    dbginfo: String,
+    sources: Vec<String>,
+    // `sources`, as seen by `Error::source`
+    source_msgs: Option<Box<SourceMsg>>,
+    cause: Option<Link>,
+    branches: Vec<Box<dyn RucError>>,
+}
+
+impl Copied {
+    fn chain(e: &dyn RucError) -> Box<dyn RucError> {
+        let levels = core::iter::successors(Some(e), |c| c.cause());
+        let levels = levels.collect::<Vec<_>>();
+        levels
+            .into_iter()
+            .rev()
+            .fold(None, |cause, c| {
+                let sources = c
+                    .top_sources()
+                    .into_iter()
+                    .map(|s| s.to_owned())
+                    .collect::<Vec<_>>();
+                Some(Box::new(Copied {
+                    type_id: c.type_id(),
+                    type_name: c.type_name().to_owned(),
+                    is_remote: c.is_remote(),
+                    location: c
+                        .location()
+                        .map(|(f, l, col)| (f.to_owned(), l, col)),
+                    code: c.top_code(),
+                    fields: c
+                        .top_fields()
+                        .into_iter()
+                        .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                        .collect(),
+                    spans: c
+                        .top_spans()
+                        .into_iter()
+                        .map(|s| s.to_owned())
+                        .collect(),
+                    thread: c.top_thread().map(|t| t.to_owned()),
+                    msg: c.get_top_msg(),
+                    dbginfo: c.get_top_msg_with_dbginfo(),
+                    source_msgs: SourceMsg::chain(sources.clone()),
+                    sources,
+                    cause: cause.map(Link),
+                    branches: c
+                        .branches()
+                        .iter()
+                        .map(|b| Copied::chain(b.as_ref()))
+                        .collect(),
+                }) as Box<dyn RucError>)
+            })
+            .unwrap() // at least one level
+    }
+}
+
+impl Display for Copied {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.generate_log(None))
+    }
+}
+
+impl RucError for Copied {
+    fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    fn is_remote(&self) -> bool {
+        self.is_remote
+    }
+
+    fn location(&self) -> Option<(&str, u32, u32)> {
+        self.location.as_ref().map(|(f, l, c)| (f.as_str(), *l, *c))
+    }
+
+    fn top_code(&self) -> Option<i32> {
+        self.code
+    }
+
+    fn top_fields(&self) -> Vec<(&str, &str)> {
+        self.fields
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect()
+    }
+
+    fn top_spans(&self) -> Vec<&str> {
+        self.spans.iter().map(|s| s.as_str()).collect()
+    }
+
+    fn top_thread(&self) -> Option<&str> {
+        self.thread.as_deref()
+    }
+
+    fn top_sources(&self) -> Vec<&str> {
+        self.sources.iter().map(|s| s.as_str()).collect()
+    }
+
+    fn get_top_msg(&self) -> String {
+        self.msg.clone()
+    }
+
+    fn get_lowest_msg(&self) -> String {
+        self.get_lowest_err().get_top_msg()
+    }
+
+    fn get_lowest_err(&self) -> &dyn RucError {
+        match self.cause() {
+            Some(c) => c.get_lowest_err(),
+            None => self,
+        }
+    }
+
+    fn get_top_msg_with_dbginfo(&self) -> String {
+        self.dbginfo.clone()
+    }
+
+    fn cause(&self) -> Option<&dyn RucError> {
+        self.cause.as_ref().map(|l| l.0.as_ref())
+    }
+
+    fn branches(&self) -> &[Box<dyn RucError>] {
+        &self.branches
+    }
+
+    fn std_source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self.cause.as_ref() {
+            Some(l) => Some(l),
+            None => self.source_msgs.as_deref().map(|s| s as _),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::*;
+    use std::{cell::Cell, sync::OnceLock};
+
+    fn assert_sync<T: Send + Sync + 'static>(_: &T) {}
+
+    // `Send` but not `Sync`
+    #[derive(Debug)]
+    struct Counter(Cell<u32>);
+
+    impl Display for Counter {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(f, "counter: {}", self.0.get())
+        }
+    }
+
+    #[test]
+    fn t_shared() {
+        let e = Err::<(), _>(eg!(code = 7, "root"))
+            .c(d!("top"))
+            .unwrap_err();
+
+        let shared = SharedError::from(e);
+        assert_sync(&shared);
+        let cell = OnceLock::new();
+        cell.set(shared.clone()).unwrap();
+
+        let waiters = (0..4)
+            .map(|_| {
+                let e = cell.get().unwrap().clone();
+                std::thread::spawn(move || {
+                    (e.get_lowest_msg(), e.code(), e.type_ids().len())
+                })
+            })
+            .collect::<Vec<_>>();
+        for w in waiters {
+            assert_eq!(w.join().unwrap(), ("root".to_owned(), Some(7), 2));
+        }
+
+        assert!(shared.ptr_eq(cell.get().unwrap()));
+        assert_eq!(shared.to_string(), cell.get().unwrap().to_string());
+        assert!(shared.with(|e| e.find::<String>().is_some()));
+        assert!(shared.stringify_chain(None).contains("Caused By: "));
+
+        // usable where `Error + Send + Sync + 'static` is required
+        let boxed: Box<dyn std::error::Error + Send + Sync> =
+            Box::new(shared.clone());
+        assert_eq!(boxed.source().unwrap().to_string(), "root");
+
+        // still shared by `cell`
+        let back: Box<dyn RucError> = shared.into();
+        assert!(back.msg_has_overlap(eg!("top").as_ref()));
+        assert!(back.msg_has_overlap(cell.get().unwrap()));
+    }
+
+    #[test]
+    fn t_shared_copied() {
+        let e = Err::<(), _>(std::io::Error::other("disk"))
+            .c(d!(code = 5, "middle"; path = "/tmp/x"))
+            .c(d!("top"))
+            .unwrap_err();
+        let shared = SharedError::from(e);
+
+        // still shared by `shared`, every level is copied
+        let e = Err::<(), _>(shared.clone()).c(d!("outer")).unwrap_err();
+        assert_eq!(e.type_ids().len(), 4);
+        assert!(e.lowest_is_type(&std::io::Error::other("")));
+        assert!(e.contains_type(&std::io::Error::other("")));
+        assert_eq!(e.get_lowest_msg(), "disk");
+        assert_eq!(e.codes(), [5]);
+        assert_eq!(e.fields().collect::<Vec<_>>(), [("path", "/tmp/x")]);
+        let s = e.stringify_chain(None);
+        let pos = ["outer", "top", "middle", "disk"].map(|m| s.find(m));
+        assert!(pos.iter().all(|p| p.is_some()), "{s}");
+        assert!(pos.is_sorted(), "{s}");
+
+        // the same through a boxed handle
+        let boxed: Box<dyn RucError> = shared.clone().into();
+        let e2 = Err::<(), _>(boxed).c(d!("outer")).unwrap_err();
+        assert_eq!(e2.type_ids().len(), 4);
+        assert_eq!(e2.get_lowest_msg(), "disk");
+
+        // the original values stay with the shared chain
+        assert!(e.downcast_ref::<std::io::Error>().is_none());
+        assert!(shared.with(|e| e.downcast_ref::<std::io::Error>().is_some()));
+    }
+
+    #[test]
+    fn t_shared_send_only() {
+        let e = Err::<(), _>(Counter(Cell::new(3)))
+            .c(d!("top"))
+            .unwrap_err();
+        let shared = SharedError::from(e);
+        let s = shared.clone();
+        let msg = std::thread::spawn(move || s.get_lowest_msg());
+        assert_eq!(msg.join().unwrap(), "counter: 3");
+
+        let n =
+            shared.with(|e| e.downcast_ref::<Counter>().map(|c| c.0.get()));
+        assert_eq!(n, Some(3));
+
+        // the last handle gives the original chain back
+        let back: Box<dyn RucError> = shared.into();
+        assert!(back.downcast_ref::<Counter>().is_some());
+    }
+}
@@ -0,0 +1,109 @@
+//!
+//! # Output sink
+//!
+//! All logs of `info!`, `pnk!`, `die!`, [`RucError::print`]
+//! and the [panic hook](super::install_panic_hook)
+//! go through the global sink, default to stderr.
+//!
+
+use super::{LogLevel, RucError};
+use std::sync::{Arc, RwLock};
+
+static SINK: RwLock<Option<Arc<dyn LogSink>>> = RwLock::new(None);
+
+/// Receiver of all the logs
+pub trait LogSink: Send + Sync {
+    /// - `rendered`: the text that would be printed to stderr by default
+    /// - `err`: the structured error, `None` for plain messages(`die!`)
+    fn log(&self, level: LogLevel, rendered: &str, err: Option<&dyn RucError>);
+}
+
+impl<F> LogSink for F
+where
+    F: Fn(LogLevel, &str, Option<&dyn RucError>) + Send + Sync,
+{
+    fn log(
+        &self,
+        level: LogLevel,
+        rendered: &str,
+        err: Option<&dyn RucError>,
+    ) {
+        self(level, rendered, err)
+    }
+}
+
+/// Route all subsequent logs to `sink`, return the previous one
+pub fn set_sink(sink: impl LogSink + 'static) -> Option<Arc<dyn LogSink>> {
+    SINK.write()
+        .unwrap_or_else(|e| e.into_inner())
+        .replace(Arc::new(sink))
+}
+
+/// Restore the default(stderr) sink, return the previous one
+pub fn reset_sink() -> Option<Arc<dyn LogSink>> {
+    SINK.write().unwrap_or_else(|e| e.into_inner()).take()
+}
+
+/// Send a log entry to the current sink
+pub fn emit(level: LogLevel, rendered: &str, err: Option<&dyn RucError>) {
+    // clone out of the lock, so a sink can call `set_sink` itself
+    let sink = SINK.read().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Some(s) = sink {
+        s.log(level, rendered, err);
+    } else {
+        eprintln!("{}", rendered);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+    use std::sync::Mutex;
+
+    static CAPTURED: Mutex<Vec<(LogLevel, String, Option<String>)>> =
+        Mutex::new(vec![]);
+
+    #[test]
+    fn t_sink() {
+        set_sink(
+            |level: LogLevel, rendered: &str, err: Option<&dyn RucError>| {
+                // other tests may log concurrently
+                if rendered.contains("t_sink") {
+                    CAPTURED.lock().unwrap().push((
+                        level,
+                        rendered.to_owned(),
+                        err.map(|e| e.get_lowest_msg()),
+                    ));
+                }
+            },
+        );
+
+        info_omit!(Err::<(), _>(eg!("t_sink root")), "t_sink info");
+        eg!("t_sink print").print(None);
+        eg!("t_sink warn").print(Some("warn"));
+        // the panic hook is process-wide, see `tests/panic_hook.rs`
+        let r = std::panic::catch_unwind(|| {
+            crate::pnk!(Err::<(), _>(eg!("t_sink pnk")))
+        });
+        assert!(r.is_err());
+
+        reset_sink();
+
+        let captured = CAPTURED.lock().unwrap();
+        assert_eq!(captured.len(), 4);
+
+        assert_eq!(captured[0].0, LogLevel::Info);
+        assert!(captured[0].1.contains("INFO"));
+        assert_eq!(captured[0].2.as_deref(), Some("t_sink root"));
+
+        assert_eq!(captured[1].0, LogLevel::Error);
+        assert_eq!(captured[1].2.as_deref(), Some("t_sink print"));
+
+        // the level follows the prefix
+        assert_eq!(captured[2].0, LogLevel::Warn);
+        assert_eq!(captured[2].2.as_deref(), Some("t_sink warn"));
+
+        assert_eq!(captured[3].0, LogLevel::Error);
+        assert_eq!(captured[3].2.as_deref(), Some("t_sink pnk"));
+    }
+}
@@ -0,0 +1,181 @@
+//!
+//! # Execution context
+//!
+//! A pid is useless to tell apart the hundreds of tasks inside an async
+//! runtime, so a log can optionally carry:
+//!
+//! - the name and id of the current thread, see [`set_record_thread`]
+//! - a user-maintained stack of "spans", e.g. request ids,
+//!   see [`enter_span`] and [`in_span`]
+//!
+//! Both are recorded in the log header and in each [`SimpleMsg`](super::SimpleMsg).
+//!
+
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+    task::{Context, Poll},
+};
+
+static RECORD_THREAD: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static SPANS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Record the current thread in logs or not, default to `false`
+pub fn set_record_thread(on: bool) {
+    RECORD_THREAD.store(on, Ordering::Relaxed);
+}
+
+/// Check if the current thread is recorded in logs
+pub fn record_thread() -> bool {
+    RECORD_THREAD.load(Ordering::Relaxed)
+}
+
+/// `name#id` of the current thread if [`record_thread`], e.g. `main#1`
+pub fn current_thread() -> Option<String> {
+    record_thread().then(thread_label)
+}
+
+// `name#id` of the current thread
+pub(super) fn thread_label() -> String {
+    let t = std::thread::current();
+    let id = format!("{:?}", t.id());
+    let id = id.trim_start_matches("ThreadId(").trim_end_matches(')');
+    format!("{}#{}", t.name().unwrap_or("<unnamed>"), id)
+}
+
+/// The span stack of the current thread, from outermost to innermost
+pub fn current_spans() -> Vec<String> {
+    SPANS.with(|s| s.borrow().clone())
+}
+
+/// Push a span onto the stack of the current thread,
+/// it is popped when the returned guard is dropped.
+///
+/// Do not hold the guard across an `.await`, use [`in_span`] instead.
+pub fn enter_span(name: impl Into<String>) -> SpanGuard {
+    SPANS.with(|s| s.borrow_mut().push(name.into()));
+    SpanGuard(())
+}
+
+/// Pop the span pushed by [`enter_span`] on drop
+#[must_use = "the span is popped immediately if the guard is not held"]
+pub struct SpanGuard(());
+
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        SPANS.with(|s| s.borrow_mut().pop());
+    }
+}
+
+/// Run `fut` inside a span, which is entered on every poll,
+/// so it follows the task across threads of any async runtime.
+pub fn in_span<F: Future>(name: impl Into<String>, fut: F) -> InSpan<F> {
+    InSpan {
+        name: name.into(),
+        fut: Box::pin(fut),
+    }
+}
+
+/// The future returned by [`in_span`]
+pub struct InSpan<F: Future> {
+    name: String,
+    fut: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for InSpan<F> {
+    type Output = F::Output;
+
+    fn poll(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<F::Output> {
+        let _guard = enter_span(self.name.clone());
+        self.fut.as_mut().poll(cx)
+    }
+}
+
+// `[thread: main#1] [spans: a > b]`, empty parts are omitted
+pub(super) fn header_ctx() -> String {
+    let mut res = String::new();
+    if let Some(t) = current_thread() {
+        res.push_str(&format!(" [thread: {t}]"));
+    }
+    let spans = current_spans();
+    if !spans.is_empty() {
+        res.push_str(&format!(" [spans: {}]", spans.join(" > ")));
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::*;
+    use std::task::Waker;
+
+    #[test]
+    fn t_spans() {
+        assert!(current_spans().is_empty());
+        let e = {
+            let _a = enter_span("req-1");
+            let _b = enter_span("db");
+            assert!(header_ctx().contains("[spans: req-1 > db]"));
+            eg!("failed")
+        };
+        assert!(current_spans().is_empty());
+        assert_eq!(e.top_spans(), ["req-1", "db"]);
+        assert!(e.stringify_chain(None).contains("req-1 > db"));
+
+        // entered on every poll, left in between
+        let mut polls = 0;
+        let mut fut = in_span(
+            "task-7",
+            std::future::poll_fn(|_| {
+                polls += 1;
+                if polls < 2 {
+                    assert_eq!(current_spans(), ["task-7"]);
+                    return Poll::Pending;
+                }
+                Poll::Ready(Err::<(), _>(eg!("inner")).c(d!("outer")))
+            }),
+        );
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(Pin::new(&mut fut).poll(&mut cx).is_pending());
+        assert!(current_spans().is_empty());
+        let Poll::Ready(res) = Pin::new(&mut fut).poll(&mut cx) else {
+            panic!("not ready");
+        };
+        let e = res.unwrap_err();
+        assert!(current_spans().is_empty());
+        assert_eq!(e.top_spans(), ["task-7"]);
+        assert_eq!(e.cause().unwrap().top_spans(), ["task-7"]);
+
+        // captured when the error is built, not by `d!()`
+        let msg = {
+            let _a = enter_span("req-2");
+            d!("lazy")
+        };
+        let e: Box<dyn RucError> = msg.into();
+        assert!(e.top_spans().is_empty());
+    }
+
+    #[test]
+    fn t_record_thread() {
+        let h = std::thread::Builder::new()
+            .name("worker".to_owned())
+            .spawn(|| {
+                set_record_thread(true);
+                let e = eg!();
+                set_record_thread(false);
+                e
+            })
+            .unwrap();
+        let e = h.join().unwrap();
+        assert!(e.top_thread().unwrap().starts_with("worker#"));
+    }
+}
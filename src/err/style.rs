@@ -0,0 +1,267 @@
+//!
+//! # Output style
+//!
+//! The `ansi` and `compact` features only decide the default style,
+//! it can be switched at runtime by [`set_log_style`],
+//! or by the `RUC_LOG_STYLE` env var:
+//!
+//! - `ansi`: colorful tree view
+//! - `plain`: non-color tree view
+//! - `compact`: non-color single-line view
+//! - `compact-ansi`: colorful single-line view
+//! - `auto`: `ansi` if stderr is a terminal, `compact` otherwise
+//!
+
+#[cfg(feature = "std")]
+use crate::common::DateTimeFormat;
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+#[cfg(feature = "std")]
+use std::{io::IsTerminal, sync::RwLock};
+
+const ANSI: u8 = 0b01;
+const COMPACT: u8 = 0b10;
+const UNSET: u8 = u8::MAX;
+
+static STYLE: AtomicU8 = AtomicU8::new(UNSET);
+
+// the fields of `RenderPolicy`, `usize::MAX` means `None`
+static COLLAPSE_REPEATS: AtomicBool = AtomicBool::new(false);
+static MAX_DEPTH: AtomicUsize = AtomicUsize::new(usize::MAX);
+static MAX_MSG_LEN: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+// `None` means `DateTimeFormat::default()`
+#[cfg(feature = "std")]
+static TIME_FORMAT: RwLock<Option<DateTimeFormat>> = RwLock::new(None);
+
+/// How error logs are rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogStyle {
+    /// colorize with ANSI escape codes
+    pub ansi: bool,
+    /// render the whole chain in one line
+    pub compact: bool,
+}
+
+impl LogStyle {
+    /// The style selected by the `ansi` and `compact` features
+    pub const fn from_features() -> Self {
+        LogStyle {
+            ansi: cfg!(feature = "ansi"),
+            compact: cfg!(feature = "compact"),
+        }
+    }
+
+    /// Colorful tree view on a terminal,
+    /// non-color single-line view otherwise(files, pipes, etc.)
+    #[cfg(feature = "std")]
+    pub fn auto() -> Self {
+        let tty = std::io::stderr().is_terminal();
+        LogStyle {
+            ansi: tty,
+            compact: !tty,
+        }
+    }
+
+    /// Parse a `RUC_LOG_STYLE` value, `None` if unknown
+    pub fn parse(s: &str) -> Option<Self> {
+        let (ansi, compact) = match s.trim().to_ascii_lowercase().as_str() {
+            "ansi" => (true, false),
+            "plain" => (false, false),
+            "compact" => (false, true),
+            "compact-ansi" => (true, true),
+            #[cfg(feature = "std")]
+            "auto" => return Some(Self::auto()),
+            _ => return None,
+        };
+        Some(LogStyle { ansi, compact })
+    }
+
+    #[cfg(feature = "std")]
+    fn from_env() -> Self {
+        std::env::var("RUC_LOG_STYLE")
+            .ok()
+            .and_then(|s| Self::parse(&s))
+            .unwrap_or_else(Self::from_features)
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn from_env() -> Self {
+        Self::from_features()
+    }
+
+    const fn to_bits(self) -> u8 {
+        (if self.ansi { ANSI } else { 0 })
+            | (if self.compact { COMPACT } else { 0 })
+    }
+
+    const fn from_bits(bits: u8) -> Self {
+        LogStyle {
+            ansi: 0 != bits & ANSI,
+            compact: 0 != bits & COMPACT,
+        }
+    }
+
+    pub(crate) const fn delimiter(self) -> &'static str {
+        if self.compact { " 》" } else { "\n" }
+    }
+
+    pub(crate) const fn indent(self) -> &'static str {
+        if self.compact { "" } else { "    " }
+    }
+
+    pub(crate) const fn pretty(self) -> [&'static str; 2] {
+        match (self.compact, self.ansi) {
+            (true, _) => ["", ""],
+            (false, true) => ["├──", "└──"],
+            (false, false) => ["|--", "`--"],
+        }
+    }
+}
+
+impl Default for LogStyle {
+    fn default() -> Self {
+        Self::from_features()
+    }
+}
+
+/// Get the current output style,
+/// initialized from `RUC_LOG_STYLE` at the first call
+pub fn log_style() -> LogStyle {
+    let bits = STYLE.load(Ordering::Relaxed);
+    if UNSET != bits {
+        return LogStyle::from_bits(bits);
+    }
+    let style = LogStyle::from_env();
+    // a concurrent `set_log_style` wins over the env var
+    match STYLE.compare_exchange(
+        UNSET,
+        style.to_bits(),
+        Ordering::Relaxed,
+        Ordering::Relaxed,
+    ) {
+        Ok(_) => style,
+        Err(bits) => LogStyle::from_bits(bits),
+    }
+}
+
+/// Set the output style of all subsequent logs
+pub fn set_log_style(style: LogStyle) {
+    STYLE.store(style.to_bits(), Ordering::Relaxed);
+}
+
+/// How long error chains are shortened, all disabled by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RenderPolicy {
+    /// collapse consecutive identical levels(same message and location)
+    /// into one, marked as "repeated N times"
+    pub collapse_repeats: bool,
+    /// max number of levels rendered for each chain,
+    /// the middle ones are elided, the root cause is always kept
+    pub max_depth: Option<usize>,
+    /// max number of chars of each message
+    pub max_msg_len: Option<usize>,
+}
+
+/// Get the current render policy
+pub fn render_policy() -> RenderPolicy {
+    let opt = |n: &AtomicUsize| {
+        Some(n.load(Ordering::Relaxed)).filter(|n| usize::MAX != *n)
+    };
+    RenderPolicy {
+        collapse_repeats: COLLAPSE_REPEATS.load(Ordering::Relaxed),
+        max_depth: opt(&MAX_DEPTH),
+        max_msg_len: opt(&MAX_MSG_LEN),
+    }
+}
+
+/// Set the render policy of all subsequent logs
+pub fn set_render_policy(policy: RenderPolicy) {
+    let n = policy.max_depth.unwrap_or(usize::MAX);
+    MAX_DEPTH.store(n, Ordering::Relaxed);
+    let n = policy.max_msg_len.unwrap_or(usize::MAX);
+    MAX_MSG_LEN.store(n, Ordering::Relaxed);
+    COLLAPSE_REPEATS.store(policy.collapse_repeats, Ordering::Relaxed);
+}
+
+/// Get the datetime format of log headers
+#[cfg(feature = "std")]
+pub fn log_time_format() -> DateTimeFormat {
+    TIME_FORMAT
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
+/// Set the datetime format of log headers(and of `LogRecord::time`),
+/// e.g. `DateTimeFormat::rfc3339().utc().precision(Precision::Millis)`
+#[cfg(feature = "std")]
+pub fn set_log_time_format(f: DateTimeFormat) {
+    *TIME_FORMAT.write().unwrap_or_else(|e| e.into_inner()) = Some(f);
+}
+
+// `ts` in the format of log headers
+#[cfg(feature = "std")]
+pub(super) fn log_time(ts: std::time::SystemTime) -> String {
+    match &*TIME_FORMAT.read().unwrap_or_else(|e| e.into_inner()) {
+        Some(f) => f.format(ts),
+        None => crate::common::gen_datetime(
+            ts.duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs() as i64),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn t_parse() {
+        assert_eq!(
+            LogStyle::parse("Compact"),
+            Some(LogStyle {
+                ansi: false,
+                compact: true
+            })
+        );
+        assert_eq!(
+            LogStyle::parse(" plain "),
+            Some(LogStyle {
+                ansi: false,
+                compact: false
+            })
+        );
+        #[cfg(feature = "std")]
+        assert!(LogStyle::parse("auto").is_some());
+        assert!(LogStyle::parse("rainbow").is_none());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn t_log_time_format() {
+        use crate::common::Precision;
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let t = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        assert_eq!(log_time(t), crate::common::gen_datetime(1_700_000_000));
+        assert_eq!(log_time_format(), DateTimeFormat::default());
+
+        let f = DateTimeFormat::rfc3339().utc().precision(Precision::Millis);
+        set_log_time_format(f.clone());
+        assert_eq!(log_time_format(), f);
+        assert_eq!(log_time(t), "2023-11-14T22:13:20.123Z");
+        set_log_time_format(DateTimeFormat::default());
+        assert_eq!(log_time(t), crate::common::gen_datetime(1_700_000_000));
+    }
+
+    #[test]
+    fn t_bits_roundtrip() {
+        for ansi in [true, false] {
+            for compact in [true, false] {
+                let s = LogStyle { ansi, compact };
+                assert_eq!(s, LogStyle::from_bits(s.to_bits()));
+            }
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//!
+//! # Test support
+//!
+//! Assertions over error chains, and stable snapshots of them
+//! for golden-output tests.
+//!
+//! ```
+//! use ruc::{testing::snapshot, *};
+//!
+//! fn load() -> Result<()> {
+//!     std::fs::read("/nonexistent").c(d!(code = 2, "load cfg"))?;
+//!     Ok(())
+//! }
+//!
+//! let res = load();
+//! assert_err_contains!(res, "load cfg");
+//! assert_chain_len!(res, 2);
+//! assert_root_type!(res, std::io::Error);
+//!
+//! let s = snapshot(res.as_ref().unwrap_err().as_ref());
+//! assert!(s.starts_with("ERROR: load cfg [code: 2] @ "));
+//! ```
+//!
+
+use super::{Result, RucError};
+use std::path::Path;
+
+/// Anything holding an error chain:
+/// `ruc::Result<T>`, `Box<dyn RucError>` and `&dyn RucError`
+pub trait ErrChain {
+    /// the chain, `None` if there is no error
+    fn err_chain(&self) -> Option<&dyn RucError>;
+}
+
+impl<T> ErrChain for Result<T> {
+    fn err_chain(&self) -> Option<&dyn RucError> {
+        self.as_ref().err().map(|e| e.as_ref())
+    }
+}
+
+impl ErrChain for Box<dyn RucError> {
+    fn err_chain(&self) -> Option<&dyn RucError> {
+        Some(self.as_ref())
+    }
+}
+
+impl ErrChain for &dyn RucError {
+    fn err_chain(&self) -> Option<&dyn RucError> {
+        Some(*self)
+    }
+}
+
+/// Get the chain, panic if there is no error
+#[track_caller]
+pub fn expect_err(e: &impl ErrChain) -> &dyn RucError {
+    match e.err_chain() {
+        Some(e) => e,
+        None => panic!("expected an error, got `Ok`"),
+    }
+}
+
+/// Number of levels, the top one and all its causes,
+/// branches are not counted
+pub fn chain_len(e: &dyn RucError) -> usize {
+    core::iter::successors(Some(e), |e| e.cause()).count()
+}
+
+/// Check if the message of any level(branches included) contains `pat`
+pub fn chain_contains(e: &dyn RucError, pat: &str) -> bool {
+    core::iter::successors(Some(e), |e| e.cause()).any(|c| {
+        c.get_top_msg().contains(pat)
+            || c.top_sources().iter().any(|s| s.contains(pat))
+            || c.branches().iter().any(|b| chain_contains(b.as_ref(), pat))
+    })
+}
+
+/// Render a chain without anything varying between runs or machines:
+/// no header(time, pid, etc.), no colors, no threads or spans,
+/// and locations are reduced to file names, without directories,
+/// line or column numbers.
+///
+/// ```text
+/// ERROR: load cfg [code: 2] @ main.rs
+/// Caused By: No such file or directory (os error 2) @ main.rs
+/// ```
+pub fn snapshot(e: &dyn RucError) -> String {
+    let mut res = String::new();
+    snapshot_chain(e, "ERROR", "", &mut res);
+    res
+}
+
+fn snapshot_chain(
+    e: &dyn RucError,
+    label: &str,
+    indent: &str,
+    res: &mut String,
+) {
+    let mut label = label;
+    for c in core::iter::successors(Some(e), |e| e.cause()) {
+        let msg = c.get_top_msg().replace('\n', &format!("\n{indent}  "));
+        res.push_str(&format!("{indent}{label}: {msg}"));
+        if let Some(code) = c.top_code() {
+            res.push_str(&format!(" [code: {code}]"));
+        }
+        let fields = c.top_fields();
+        if !fields.is_empty() {
+            let fields = fields
+                .iter()
+                .map(|(k, v)| format!("{k}: {v}"))
+                .collect::<Vec<_>>();
+            res.push_str(&format!(" {{{}}}", fields.join(", ")));
+        }
+        if let Some((file, _, _)) = c.location() {
+            let name = Path::new(file).file_name().unwrap_or_default();
+            res.push_str(&format!(" @ {}", name.to_string_lossy()));
+        }
+        res.push('\n');
+        let branches = c.branches();
+        let sub_indent = format!("{indent}    ");
+        for (i, b) in branches.iter().enumerate() {
+            let label = format!("Branch [{}/{}]", i + 1, branches.len());
+            snapshot_chain(b.as_ref(), &label, &sub_indent, res);
+        }
+        label = "Caused By";
+        for src in c.top_sources() {
+            let src = src.replace('\n', &format!("\n{indent}  "));
+            res.push_str(&format!("{indent}{label}: {src}\n"));
+        }
+    }
+}
+
+/// Assert that the result is an error,
+/// and the message of some level contains the given pattern
+#[macro_export]
+macro_rules! assert_err_contains {
+    ($res:expr, $pat:expr $(,)?) => {{
+        // keep the temporary `$res` alive
+        match $crate::err::testing::expect_err(&$res) {
+            e => {
+                let pat: &str = $pat;
+                assert!(
+                    $crate::err::testing::chain_contains(e, pat),
+                    "no level contains {:?}:\n{}",
+                    pat,
+                    $crate::err::testing::snapshot(e)
+                );
+            }
+        }
+    }};
+}
+
+/// Assert that the result is an error with the given number of levels,
+/// see [`chain_len`](crate::err::testing::chain_len)
+#[macro_export]
+macro_rules! assert_chain_len {
+    ($res:expr, $len:expr $(,)?) => {{
+        // keep the temporary `$res` alive
+        match $crate::err::testing::expect_err(&$res) {
+            e => {
+                let len: usize = $len;
+                assert_eq!(
+                    $crate::err::testing::chain_len(e),
+                    len,
+                    "chain length mismatch:\n{}",
+                    $crate::err::testing::snapshot(e)
+                );
+            }
+        }
+    }};
+}
+
+/// Assert that the result is an error,
+/// and its lowest(root cause) error is of the given type
+#[macro_export]
+macro_rules! assert_root_type {
+    ($res:expr, $ty:ty $(,)?) => {{
+        // keep the temporary `$res` alive
+        match $crate::err::testing::expect_err(&$res) {
+            e => {
+                assert!(
+                    e.downcast_ref::<$ty>().is_some(),
+                    "the root cause is `{}`, not `{}`:\n{}",
+                    $crate::err::RucError::type_name(
+                        $crate::err::RucError::get_lowest_err(e)
+                    ),
+                    stringify!($ty),
+                    $crate::err::testing::snapshot(e)
+                );
+            }
+        }
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn t_assertions() {
+        let res = "x".parse::<u8>().c(d!("parse")).c(d!("load"));
+        assert_err_contains!(res, "invalid digit");
+        assert_chain_len!(res, 3);
+        assert_root_type!(res, core::num::ParseIntError);
+
+        let e = res.unwrap_err();
+        assert_chain_len!(e, 3);
+        assert_root_type!(e.as_ref(), core::num::ParseIntError);
+
+        // the branches are searched too
+        let multi =
+            collect_all([eg!("disk a"), eg!("disk b")].map(Err::<(), _>))
+                .c(d!("all"));
+        assert_chain_len!(multi, 2);
+        assert_err_contains!(multi, "disk b");
+    }
+
+    #[test]
+    #[should_panic(expected = "no level contains")]
+    fn t_assert_err_contains_fail() {
+        assert_err_contains!("x".parse::<u8>().c(d!("parse")), "timeout");
+    }
+
+    #[test]
+    #[should_panic(expected = "expected an error")]
+    fn t_assert_ok() {
+        assert_chain_len!(Ok::<_, Box<dyn RucError>>(0), 1);
+    }
+
+    #[test]
+    fn t_snapshot() {
+        let e = collect_all(
+            ["1", "x", "300"].map(|s| s.parse::<u8>().c(d!("parse"; s = s))),
+        )
+        .c(d!(code = 400, "validate"))
+        .unwrap_err();
+        assert_eq!(
+            snapshot(e.as_ref()),
+            "\
+ERROR: validate [code: 400] @ testing.rs
+Caused By: 2 errors occurred @ testing.rs
+    Branch [1/2]: parse {s: x} @ testing.rs
+    Caused By: invalid digit found in string @ testing.rs
+    Branch [2/2]: parse {s: 300} @ testing.rs
+    Caused By: number too large to fit in target type @ testing.rs
+"
+        );
+    }
+}
@@ -0,0 +1,323 @@
+//!
+//! # Log throttling
+//!
+//! In hot loops, `info!` and its siblings would print the same chain
+//! on every failure. With a throttle window set by [`set_log_throttle`],
+//! only the first log of each call site(file, line, column) is printed
+//! within the window; when the window closes, a summary like
+//! "suppressed 4213 identical logs in 10s" is printed
+//! by a background thread.
+//!
+
+use super::{
+    LogLevel, PID, PID_NS, RucError, emit, generate_log_header, log_style,
+};
+use std::{
+    collections::HashMap,
+    panic::Location,
+    sync::{
+        Condvar, LazyLock, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+// in milliseconds, `0` means disabled
+static WINDOW: AtomicU64 = AtomicU64::new(0);
+
+static SITES: Mutex<Sites> = Mutex::new(Sites(None));
+
+// wakes the flusher when a window gets its first suppressed log
+static PENDING: Condvar = Condvar::new();
+
+// prints the summaries of closed windows, started on the first suppression
+static FLUSHER: LazyLock<()> = LazyLock::new(|| {
+    std::thread::Builder::new()
+        .name("ruc-log-throttle".to_owned())
+        .spawn(flush_loop)
+        .expect("failed to spawn the log throttle thread");
+});
+
+/// Suppress repeated logs of the same call site within `window`,
+/// `None` to disable(the default)
+pub fn set_log_throttle(window: Option<Duration>) {
+    let ms = window.map(|w| w.as_millis().max(1) as u64).unwrap_or(0);
+    WINDOW.store(ms, Ordering::Relaxed);
+}
+
+/// Get the current throttle window
+pub fn log_throttle() -> Option<Duration> {
+    match WINDOW.load(Ordering::Relaxed) {
+        0 => None,
+        ms => Some(Duration::from_millis(ms)),
+    }
+}
+
+/// Print the summaries of all the call sites with suppressed logs
+/// at once, e.g. before exiting, instead of waiting for their windows
+/// to close.
+pub fn flush_log_throttle() {
+    let pending = SITES.lock().unwrap_or_else(|e| e.into_inner()).drain();
+    pending.into_iter().for_each(print_summary);
+}
+
+// `info!`, `warn!`, etc. print through this
+pub(super) fn print_throttled(
+    e: &dyn RucError,
+    level: LogLevel,
+    loc: &'static Location<'static>,
+) {
+    let Some(window) = log_throttle() else {
+        e.print_level(level);
+        return;
+    };
+    let key = (loc.file(), loc.line(), loc.column());
+    let hit = SITES.lock().unwrap_or_else(|e| e.into_inner()).hit(
+        key,
+        level,
+        window,
+        Instant::now(),
+    );
+    match hit {
+        Hit::First => e.print_level(level),
+        Hit::Suppressed(1) => {
+            LazyLock::force(&FLUSHER);
+            PENDING.notify_one();
+        }
+        Hit::Suppressed(_) => {}
+        Hit::After(summary) => {
+            print_summary(summary);
+            e.print_level(level);
+        }
+    }
+}
+
+fn flush_loop() {
+    let mut sites = SITES.lock().unwrap_or_else(|e| e.into_inner());
+    loop {
+        let (closed, next) = sites.expire(Instant::now());
+        if !closed.is_empty() {
+            // print without holding the lock
+            drop(sites);
+            closed.into_iter().for_each(print_summary);
+            sites = SITES.lock().unwrap_or_else(|e| e.into_inner());
+            continue;
+        }
+        sites = match next {
+            Some(at) => {
+                let wait = at.saturating_duration_since(Instant::now());
+                PENDING
+                    .wait_timeout(sites, wait)
+                    .unwrap_or_else(|e| e.into_inner())
+                    .0
+            }
+            None => PENDING.wait(sites).unwrap_or_else(|e| e.into_inner()),
+        };
+    }
+}
+
+fn print_summary(s: Summary) {
+    let (file, line, column) = s.key;
+    let msg = format!(
+        "{}{}{}: suppressed {} identical logs of {}:{}:{} in {}s",
+        generate_log_header(&PID_NS, *PID),
+        log_style().delimiter(),
+        s.level.as_str(),
+        s.suppressed,
+        file,
+        line,
+        column,
+        s.elapsed.as_secs(),
+    );
+    emit(s.level, &msg, None);
+}
+
+type Key = (&'static str, u32, u32);
+
+struct Site {
+    since: Instant,
+    until: Instant,
+    level: LogLevel,
+    suppressed: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Summary {
+    key: Key,
+    level: LogLevel,
+    suppressed: u64,
+    elapsed: Duration,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Hit {
+    // print it
+    First,
+    // drop it, the number of suppressed logs in current window
+    Suppressed(u64),
+    // the window closed unnoticed,
+    // print a summary of the suppressed ones, then print it
+    After(Summary),
+}
+
+// created lazily, `HashMap::new` is not const
+struct Sites(Option<HashMap<Key, Site>>);
+
+impl Sites {
+    fn map(&mut self) -> &mut HashMap<Key, Site> {
+        self.0.get_or_insert_with(HashMap::new)
+    }
+
+    fn hit(
+        &mut self,
+        key: Key,
+        level: LogLevel,
+        window: Duration,
+        now: Instant,
+    ) -> Hit {
+        let new_site = Site {
+            since: now,
+            until: now + window,
+            level,
+            suppressed: 0,
+        };
+        let Some(site) = self.map().get_mut(&key) else {
+            self.map().insert(key, new_site);
+            return Hit::First;
+        };
+        if now < site.until {
+            site.suppressed += 1;
+            return Hit::Suppressed(site.suppressed);
+        }
+        let old = core::mem::replace(site, new_site);
+        if 0 == old.suppressed {
+            Hit::First
+        } else {
+            Hit::After(old.summary(key, now))
+        }
+    }
+
+    // remove the sites whose window has closed by `now`,
+    // return their summaries and when the next pending window closes
+    fn expire(&mut self, now: Instant) -> (Vec<Summary>, Option<Instant>) {
+        let mut closed = vec![];
+        self.map().retain(|k, s| {
+            if now < s.until {
+                return true;
+            }
+            if 0 < s.suppressed {
+                closed.push(s.summary(*k, now));
+            }
+            false
+        });
+        let next = self
+            .map()
+            .values()
+            .filter(|s| 0 < s.suppressed)
+            .map(|s| s.until)
+            .min();
+        (closed, next)
+    }
+
+    // reset all sites, return the pending summaries
+    fn drain(&mut self) -> Vec<Summary> {
+        let now = Instant::now();
+        self.map()
+            .drain()
+            .filter(|(_, s)| 0 < s.suppressed)
+            .map(|(k, s)| s.summary(k, now))
+            .collect()
+    }
+}
+
+impl Site {
+    fn summary(&self, key: Key, now: Instant) -> Summary {
+        Summary {
+            key,
+            level: self.level,
+            suppressed: self.suppressed,
+            elapsed: now.saturating_duration_since(self.since),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn t_hit() {
+        let mut sites = Sites(None);
+        let key = ("src/lib.rs", 1, 1);
+        let other = ("src/lib.rs", 2, 1);
+        let window = Duration::from_secs(10);
+        let t0 = Instant::now();
+        let at = |secs| t0 + Duration::from_secs(secs);
+        let hit = |sites: &mut Sites, key, secs| {
+            sites.hit(key, LogLevel::Info, window, at(secs))
+        };
+
+        assert_eq!(hit(&mut sites, key, 0), Hit::First);
+        assert_eq!(hit(&mut sites, other, 1), Hit::First);
+        for s in 1..10 {
+            assert_eq!(hit(&mut sites, key, s), Hit::Suppressed(s));
+        }
+        assert_eq!(
+            hit(&mut sites, key, 12),
+            Hit::After(Summary {
+                key,
+                level: LogLevel::Info,
+                suppressed: 9,
+                elapsed: Duration::from_secs(12),
+            })
+        );
+        assert_eq!(hit(&mut sites, key, 13), Hit::Suppressed(1));
+
+        // nothing suppressed, no summary
+        assert_eq!(hit(&mut sites, other, 30), Hit::First);
+
+        let pending = sites.drain();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].key, key);
+        assert_eq!(pending[0].suppressed, 1);
+        assert!(sites.map().is_empty());
+    }
+
+    #[test]
+    fn t_expire() {
+        let mut sites = Sites(None);
+        let (a, b) = (("src/a.rs", 1, 1), ("src/b.rs", 1, 1));
+        let window = Duration::from_secs(10);
+        let t0 = Instant::now();
+        let at = |secs| t0 + Duration::from_secs(secs);
+
+        sites.hit(a, LogLevel::Warn, window, at(0));
+        sites.hit(a, LogLevel::Warn, window, at(1));
+        sites.hit(b, LogLevel::Info, window, at(5));
+        // `b` has nothing to summarize, no need to wake for it
+        assert_eq!(sites.expire(at(2)), (vec![], Some(at(10))));
+
+        // closed without another log of `a`
+        let (closed, next) = sites.expire(at(11));
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].key, a);
+        assert_eq!(closed[0].suppressed, 1);
+        assert_eq!(closed[0].elapsed, Duration::from_secs(11));
+        assert_eq!(next, None);
+        assert_eq!(sites.map().len(), 1);
+
+        // a new window
+        assert_eq!(sites.hit(a, LogLevel::Warn, window, at(12)), Hit::First);
+        assert_eq!(sites.expire(at(25)).0, vec![]);
+        assert!(sites.map().is_empty());
+    }
+
+    #[test]
+    fn t_window() {
+        assert_eq!(log_throttle(), None);
+        set_log_throttle(Some(Duration::from_micros(10)));
+        assert_eq!(log_throttle(), Some(Duration::from_millis(1)));
+        set_log_throttle(None);
+        assert_eq!(log_throttle(), None);
+    }
+}
@@ -5,19 +5,20 @@ use reqwest::{
     StatusCode,
     blocking::{Client, ClientBuilder},
 };
-use std::{env, sync::LazyLock, time::Duration};
+use std::{sync::LazyLock, time::Duration};
 
 static TIME_OUT: LazyLock<Duration> = LazyLock::new(|| {
-    // `RUC_HTTP_TIMEOUT`: seconds, default 3, max 255;
-    // unparsable or out-of-range values fall back to 3;
+    // `RUC_HTTP_TIMEOUT`: seconds, default 3, clamped into [1, 255];
+    // a malformed value is reported and falls back to 3;
     // read once at the first request, later changes have no effect
     let default = 3;
-    let secs = if let Ok(t) = env::var("RUC_HTTP_TIMEOUT") {
-        t.parse::<u8>().unwrap_or(default)
-    } else {
-        default
-    };
-    Duration::from_secs(secs as u64)
+    let secs = warn!(crate::common::env_clamp(
+        "RUC_HTTP_TIMEOUT",
+        default,
+        1..=255
+    ))
+    .unwrap_or(default);
+    Duration::from_secs(secs)
 });
 
 static HTTP_CLI: LazyLock<Client> = LazyLock::new(|| {
@@ -1,6 +1,9 @@
 #![doc = include_str!("../README.md")]
 #![deny(warnings)]
 #![deny(missing_docs)]
+#![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
+
+extern crate alloc;
 
 pub mod common;
 pub mod err;
@@ -18,6 +21,9 @@ pub mod uau;
 #[cfg(feature = "http")]
 pub mod http;
 
+#[cfg(feature = "async")]
+pub mod aio;
+
 // DO NOT SET features!
 pub mod algo;
 
@@ -27,3 +33,9 @@ pub mod ende;
 /////////////////////////////////////////////
 
 pub use err::*;
+
+// used by the exported macros, which may expand in `no_std` crates
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::{boxed::Box, format, string::String};
+}
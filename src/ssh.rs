@@ -24,14 +24,12 @@ type UserRef<'a> = &'a str;
 
 type Port = u16;
 
-// Clamped `RUC_SSH_TIMEOUT` in seconds: default 20, upper bound 300.
+// Clamped `RUC_SSH_TIMEOUT` in seconds: default 20, upper bound 300,
+// a malformed value is reported and falls back to the default.
 #[inline(always)]
 fn ssh_timeout_secs() -> u32 {
-    env::var("RUC_SSH_TIMEOUT")
-        .ok()
-        .and_then(|t| info!(t.parse::<u32>(), t).ok())
+    warn!(crate::common::env_clamp("RUC_SSH_TIMEOUT", 20, 0..=300))
         .unwrap_or(20)
-        .min(300)
 }
 
 // Drain stdout and stderr concurrently (non-blocking interleaved reads)
@@ -0,0 +1,54 @@
+//!
+//! The panic hook is process-wide,
+//! so it is tested in its own binary.
+//!
+
+#![cfg(feature = "std")]
+
+use ruc::*;
+use std::{
+    panic::catch_unwind,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+static CAPTURED: Mutex<Vec<(String, Option<String>)>> = Mutex::new(vec![]);
+static PREV_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[test]
+fn t_panic_hook() {
+    set_sink(|_: LogLevel, rendered: &str, err: Option<&dyn RucError>| {
+        CAPTURED
+            .lock()
+            .unwrap()
+            .push((rendered.to_owned(), err.map(|e| e.get_lowest_msg())));
+    });
+    std::panic::set_hook(Box::new(|_| {
+        PREV_CALLS.fetch_add(1, Ordering::Relaxed);
+    }));
+
+    // logged before the hook exists,
+    // must not hide the next panic from the hook
+    assert!(catch_unwind(|| pnk!(Err::<(), _>(eg!("pnk")))).is_err());
+    install_panic_hook();
+    assert!(catch_unwind(|| panic!("plain panic")).is_err());
+    assert!(catch_unwind(|| die!("die")).is_err());
+
+    let captured = CAPTURED.lock().unwrap();
+    assert_eq!(captured.len(), 3);
+    assert_eq!(captured[0].1.as_deref(), Some("pnk"));
+
+    // not swallowed by the earlier `pnk!`
+    assert!(captured[1].0.contains("PANIC: "));
+    assert!(captured[1].0.contains(file!()));
+    assert_eq!(captured[1].1.as_deref(), Some("plain panic"));
+
+    // `die!` has been logged, the hook does not print it again
+    assert!(captured[2].0.contains("die"));
+    assert!(captured[2].1.is_none());
+
+    // the previous hook still sees every panic
+    assert_eq!(PREV_CALLS.load(Ordering::Relaxed), 3);
+}